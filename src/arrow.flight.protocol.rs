@@ -267,8 +267,6 @@ pub struct PutResult {
 pub mod flight_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
 
-    use crate::tracing::tracing_current_span_to_req;
-    use std::borrow::BorrowMut;
     use tonic::codegen::http::Uri;
     use tonic::codegen::*;
 
@@ -367,9 +365,9 @@ pub mod flight_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/arrow.flight.protocol.FlightService/Handshake",
             );
-            let mut tonic_request = request.into_streaming_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
-            self.inner.streaming(tonic_request, path, codec).await
+            self.inner
+                .streaming(request.into_streaming_request(), path, codec)
+                .await
         }
         ///
         /// Get a list of available streams given a particular criteria. Most flight
@@ -394,10 +392,8 @@ pub mod flight_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/arrow.flight.protocol.FlightService/ListFlights",
             );
-            let mut tonic_request = request.into_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
             self.inner
-                .server_streaming(tonic_request, path, codec)
+                .server_streaming(request.into_request(), path, codec)
                 .await
         }
         ///
@@ -426,9 +422,7 @@ pub mod flight_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/arrow.flight.protocol.FlightService/GetFlightInfo",
             );
-            let mut tonic_request = request.into_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
-            self.inner.unary(tonic_request, path, codec).await
+            self.inner.unary(request.into_request(), path, codec).await
         }
         ///
         /// For a given FlightDescriptor, get the Schema as described in Schema.fbs::Schema
@@ -450,9 +444,7 @@ pub mod flight_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/arrow.flight.protocol.FlightService/GetSchema",
             );
-            let mut tonic_request = request.into_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
-            self.inner.unary(tonic_request, path, codec).await
+            self.inner.unary(request.into_request(), path, codec).await
         }
         ///
         /// Retrieve a single stream associated with a particular descriptor
@@ -474,10 +466,8 @@ pub mod flight_service_client {
             let codec = tonic::codec::ProstCodec::default();
             let path =
                 http::uri::PathAndQuery::from_static("/arrow.flight.protocol.FlightService/DoGet");
-            let mut tonic_request = request.into_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
             self.inner
-                .server_streaming(tonic_request, path, codec)
+                .server_streaming(request.into_request(), path, codec)
                 .await
         }
         ///
@@ -502,9 +492,9 @@ pub mod flight_service_client {
             let codec = tonic::codec::ProstCodec::default();
             let path =
                 http::uri::PathAndQuery::from_static("/arrow.flight.protocol.FlightService/DoPut");
-            let mut tonic_request = request.into_streaming_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
-            self.inner.streaming(tonic_request, path, codec).await
+            self.inner
+                .streaming(request.into_streaming_request(), path, codec)
+                .await
         }
         ///
         /// Open a bidirectional data channel for a given descriptor. This
@@ -528,9 +518,9 @@ pub mod flight_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/arrow.flight.protocol.FlightService/DoExchange",
             );
-            let mut tonic_request = request.into_streaming_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
-            self.inner.streaming(tonic_request, path, codec).await
+            self.inner
+                .streaming(request.into_streaming_request(), path, codec)
+                .await
         }
         ///
         /// Flight services can support an arbitrary number of simple actions in
@@ -555,10 +545,8 @@ pub mod flight_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/arrow.flight.protocol.FlightService/DoAction",
             );
-            let mut tonic_request = request.into_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
             self.inner
-                .server_streaming(tonic_request, path, codec)
+                .server_streaming(request.into_request(), path, codec)
                 .await
         }
         ///
@@ -581,10 +569,8 @@ pub mod flight_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/arrow.flight.protocol.FlightService/ListActions",
             );
-            let mut tonic_request = request.into_request();
-            tracing_current_span_to_req(tonic_request.borrow_mut());
             self.inner
-                .server_streaming(tonic_request, path, codec)
+                .server_streaming(request.into_request(), path, codec)
                 .await
         }
     }
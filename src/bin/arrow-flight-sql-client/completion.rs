@@ -0,0 +1,399 @@
+use arrow::{
+    array::{Array, BinaryArray, StringArray},
+    datatypes::Schema,
+    error::{ArrowError, Result},
+    record_batch::RecordBatch,
+};
+use arrow_flight_sql_client::{
+    arrow_flight_protocol_sql::*,
+    client::{FlightSqlServiceClient, IpcMessage},
+    sql_info::SqlInfoValue,
+};
+use rustyline::{
+    completion::Completer, highlight::Highlighter, hint::Hinter, validate::Validator, Context,
+    Helper,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+/// The meta-commands of the shell.
+pub const META_COMMANDS: &[&str] = &[
+    "\\?",
+    "\\d",
+    "\\dn",
+    "\\dt",
+    "\\info",
+    "\\keys",
+    "\\q",
+    "\\refresh",
+];
+
+/// The names that can be completed, as they were when they were last loaded
+/// from the server.
+#[derive(Debug, Default)]
+struct Completions {
+    /// Every catalog, schema, table, column and keyword.
+    names: BTreeSet<String>,
+    /// The names that can follow a qualifier, such as the columns of a table,
+    /// by the lowercase qualifier.
+    children: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Completions {
+    fn add(&mut self, qualifiers: &[&str], name: &str) {
+        self.names.insert(name.to_string());
+        // A name can follow each of the qualifiers that end right before it,
+        // as in table.column, schema.table.column and catalog.schema.table.column.
+        for start in 0..qualifiers.len() {
+            let qualifier = qualifiers[start..].join(".").to_lowercase();
+            self.children
+                .entry(qualifier)
+                .or_default()
+                .insert(name.to_string());
+        }
+    }
+
+    fn extend(&mut self, other: Completions) {
+        self.names.extend(other.names);
+        for (qualifier, names) in other.children {
+            self.children.entry(qualifier).or_default().extend(names);
+        }
+    }
+}
+
+/// Completes meta-commands and the names of catalogs, schemas, tables,
+/// columns and keywords of the server in the shell.
+///
+/// Clones share the names, so that a refresh is seen by the editor.
+#[derive(Debug, Clone, Default)]
+pub struct ShellHelper {
+    completions: Arc<RwLock<Completions>>,
+}
+
+impl ShellHelper {
+    /// Load the names from the server, replacing those loaded before.
+    ///
+    /// The sources of names are loaded independently, so that the names of
+    /// the others are kept when one of them fails.
+    #[tracing::instrument(skip_all)]
+    pub async fn refresh(&self, client: &FlightSqlServiceClient) -> Refreshed {
+        let (completions, failures) = load_completions(client).await;
+        let names = completions.names.len();
+        *self
+            .completions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = completions;
+        Refreshed { names, failures }
+    }
+}
+
+/// The outcome of a refresh of the names to complete.
+#[derive(Debug)]
+pub struct Refreshed {
+    /// The number of names that were loaded.
+    pub names: usize,
+    /// The sources of names that could not be loaded, such as "tables", and
+    /// why.
+    pub failures: Vec<(&'static str, ArrowError)>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn load_completions(
+    client: &FlightSqlServiceClient,
+) -> (Completions, Vec<(&'static str, ArrowError)>) {
+    let mut completions = Completions::default();
+    let mut failures = vec![];
+    let mut keep = |source, result: Result<Completions>| match result {
+        Ok(loaded) => completions.extend(loaded),
+        Err(err) => failures.push((source, err)),
+    };
+
+    keep("catalogs", load_catalogs(client).await);
+    keep("schemas", load_db_schemas(client).await);
+    match load_tables(client).await {
+        Ok(batches) => {
+            keep("tables", Ok(table_completions(&batches)));
+            keep("columns", column_completions(&batches));
+        }
+        Err(err) => keep("tables", Err(err)),
+    }
+    keep("keywords", load_keywords(client).await);
+
+    (completions, failures)
+}
+
+#[tracing::instrument(skip_all)]
+async fn load_catalogs(client: &FlightSqlServiceClient) -> Result<Completions> {
+    let mut completions = Completions::default();
+    let fi = client.get_catalogs().await?;
+    for batch in client.do_get_batches(&fi).await? {
+        for catalog in string_values(&batch, "catalog_name").into_iter().flatten() {
+            completions.add(&[], &catalog);
+        }
+    }
+    Ok(completions)
+}
+
+#[tracing::instrument(skip_all)]
+async fn load_db_schemas(client: &FlightSqlServiceClient) -> Result<Completions> {
+    let mut completions = Completions::default();
+    let fi = client
+        .get_db_schemas(CommandGetDbSchemas {
+            catalog: None,
+            db_schema_filter_pattern: None,
+        })
+        .await?;
+    for batch in client.do_get_batches(&fi).await? {
+        let catalogs = string_values(&batch, "catalog_name");
+        let db_schemas = string_values(&batch, "db_schema_name");
+        for (catalog, db_schema) in catalogs.iter().zip(db_schemas) {
+            if let Some(db_schema) = db_schema {
+                completions.add(&qualifiers(&[catalog]), &db_schema);
+            }
+        }
+    }
+    Ok(completions)
+}
+
+/// The tables of the server, with their schemas.
+#[tracing::instrument(skip_all)]
+async fn load_tables(client: &FlightSqlServiceClient) -> Result<Vec<RecordBatch>> {
+    let fi = client
+        .get_tables(CommandGetTables {
+            catalog: None,
+            db_schema_filter_pattern: None,
+            table_name_filter_pattern: None,
+            table_types: vec![],
+            include_schema: true,
+        })
+        .await?;
+    Ok(client.do_get_batches(&fi).await?)
+}
+
+/// The names of the tables returned by GetTables.
+fn table_completions(batches: &[RecordBatch]) -> Completions {
+    let mut completions = Completions::default();
+    for batch in batches {
+        let catalogs = string_values(batch, "catalog_name");
+        let db_schemas = string_values(batch, "db_schema_name");
+        let tables = string_values(batch, "table_name");
+        for (row, table) in tables.iter().enumerate() {
+            if let Some(table) = table {
+                completions.add(&qualifiers(&[&catalogs[row], &db_schemas[row]]), table);
+            }
+        }
+    }
+    completions
+}
+
+/// The names of the columns in the table schemas returned by GetTables.
+fn column_completions(batches: &[RecordBatch]) -> Result<Completions> {
+    let mut completions = Completions::default();
+    for batch in batches {
+        let catalogs = string_values(batch, "catalog_name");
+        let db_schemas = string_values(batch, "db_schema_name");
+        let tables = string_values(batch, "table_name");
+        let table_schemas = batch
+            .schema()
+            .index_of("table_schema")
+            .ok()
+            .map(|index| batch.column(index).clone());
+        let table_schemas = match table_schemas
+            .as_ref()
+            .and_then(|column| column.as_any().downcast_ref::<BinaryArray>())
+        {
+            Some(table_schemas) => table_schemas,
+            None => continue,
+        };
+        for (row, table) in tables.iter().enumerate() {
+            let table = match table {
+                Some(table) if !table_schemas.is_null(row) => table,
+                _ => continue,
+            };
+            let mut table_qualifiers = qualifiers(&[&catalogs[row], &db_schemas[row]]);
+            table_qualifiers.push(table);
+            let schema = Schema::try_from(IpcMessage(table_schemas.value(row).to_vec()))?;
+            for field in schema.fields() {
+                completions.add(&table_qualifiers, field.name());
+            }
+        }
+    }
+    Ok(completions)
+}
+
+#[tracing::instrument(skip_all)]
+async fn load_keywords(client: &FlightSqlServiceClient) -> Result<Completions> {
+    let mut completions = Completions::default();
+    let infos = client
+        .get_sql_info_values(vec![
+            SqlInfo::SqlKeywords,
+            SqlInfo::SqlNumericFunctions,
+            SqlInfo::SqlStringFunctions,
+        ])
+        .await?;
+    for (_, value) in infos {
+        if let SqlInfoValue::StringList(keywords) = value {
+            for keyword in keywords {
+                completions.add(&[], &keyword);
+            }
+        }
+    }
+    Ok(completions)
+}
+
+/// The qualifiers that are present, in order.
+fn qualifiers<'a>(qualifiers: &[&'a Option<String>]) -> Vec<&'a str> {
+    qualifiers
+        .iter()
+        .filter_map(|qualifier| qualifier.as_deref())
+        .collect()
+}
+
+/// The values of the string column with the given name, or nulls when the
+/// batch has no such column.
+fn string_values(batch: &RecordBatch, name: &str) -> Vec<Option<String>> {
+    let column = batch
+        .schema()
+        .index_of(name)
+        .ok()
+        .map(|index| batch.column(index).clone());
+    match column
+        .as_ref()
+        .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+    {
+        Some(column) => column
+            .iter()
+            .map(|value| value.map(str::to_string))
+            .collect(),
+        None => vec![None; batch.num_rows()],
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == '.' || *c == '\\'))
+            .map(|(index, c)| index + c.len_utf8())
+            .unwrap_or(0);
+        let word = &line[start..pos];
+
+        if word.starts_with('\\') {
+            let commands = META_COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(|command| command.to_string())
+                .collect();
+            return Ok((start, commands));
+        }
+
+        let completions = self
+            .completions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (start, prefix, names) = match word.rfind('.') {
+            Some(dot) => (
+                start + dot + 1,
+                &word[dot + 1..],
+                completions.children.get(&word[..dot].to_lowercase()),
+            ),
+            None => (start, word, Some(&completions.names)),
+        };
+        let prefix = prefix.to_lowercase();
+        let candidates = names
+            .into_iter()
+            .flatten()
+            .filter(|name| name.to_lowercase().starts_with(&prefix))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::ArrayRef,
+        datatypes::{DataType, Field},
+        ipc::writer::{IpcDataGenerator, IpcWriteOptions},
+    };
+
+    fn schema_bytes(schema: &Schema) -> Vec<u8> {
+        let message = IpcDataGenerator::default()
+            .schema_to_bytes(schema, &IpcWriteOptions::default())
+            .ipc_message;
+        let mut bytes = (message.len() as i32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&message);
+        bytes
+    }
+
+    /// A GetTables result with the given table schemas.
+    fn tables(table_schemas: Vec<Option<&[u8]>>) -> RecordBatch {
+        let names = (0..table_schemas.len())
+            .map(|index| Some(format!("table{}", index)))
+            .collect::<StringArray>();
+        RecordBatch::try_from_iter(vec![
+            (
+                "catalog_name",
+                Arc::new(StringArray::from(vec![None; names.len()])) as ArrayRef,
+            ),
+            (
+                "db_schema_name",
+                Arc::new(StringArray::from(vec![Some("app"); names.len()])) as ArrayRef,
+            ),
+            ("table_name", Arc::new(names) as ArrayRef),
+            (
+                "table_schema",
+                Arc::new(BinaryArray::from(table_schemas)) as ArrayRef,
+            ),
+        ])
+        .unwrap()
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn completes_columns_of_the_table_schemas() {
+        let schema = schema_bytes(&Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let batches = vec![tables(vec![Some(&schema), None])];
+        let mut completions = table_completions(&batches);
+        completions.extend(column_completions(&batches).unwrap());
+        assert_eq!(completions.names, names(&["id", "table0", "table1"]));
+        assert_eq!(completions.children["app"], names(&["table0", "table1"]));
+        assert_eq!(completions.children["app.table0"], names(&["id"]));
+        assert_eq!(completions.children["table0"], names(&["id"]));
+    }
+
+    #[test]
+    fn keeps_tables_when_a_table_schema_is_invalid() {
+        let batches = vec![tables(vec![Some(b"invalid"), None])];
+        assert_eq!(
+            table_completions(&batches).names,
+            names(&["table0", "table1"])
+        );
+        assert!(column_completions(&batches).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use arrow::error::{ArrowError, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// The name of the profile that is used when none is selected.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The configuration file, with named profiles.
+///
+/// ```toml
+/// [profiles.default]
+/// hostname = "localhost"
+/// port = 52358
+///
+/// [profiles.production]
+/// hostname = "flight.example.com"
+/// port = 443
+/// tls = true
+/// username = "analyst"
+/// password-env = "PRODUCTION_PASSWORD"
+/// catalog = "warehouse"
+/// db-schema = "sales"
+/// format = "csv"
+///
+/// [profiles.production.headers]
+/// x-tenant = "acme"
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The settings of a profile, which are used for the options that are not
+/// given on the command line or through an environment variable.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Profile {
+    /// The uri of the server, used instead of the hostname and port.
+    pub uri: Option<String>,
+    pub hostname: Option<String>,
+    pub port: Option<u16>,
+    pub otlp_endpoint: Option<String>,
+    pub tls: Option<bool>,
+    pub ca_cert: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub tls_domain_name: Option<String>,
+    pub tls_insecure_skip_verify: Option<bool>,
+    pub username: Option<String>,
+    /// The environment variable that holds the password, so that it is not
+    /// stored in the configuration file.
+    pub password_env: Option<String>,
+    /// The environment variable that holds the bearer token.
+    pub token_env: Option<String>,
+    /// The catalog used when a command does not name one.
+    pub catalog: Option<String>,
+    /// The schema used when a command does not name one.
+    pub db_schema: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    pub format: Option<String>,
+}
+
+/// ~/.config/arrow-flight-sql-client/config.toml on Linux, or the equivalent
+/// configuration directory on other platforms.
+pub fn default_config_file() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("arrow-flight-sql-client").join("config.toml"))
+}
+
+/// Load the profile with the given name from the configuration file.
+///
+/// Without a name the default profile is used, if the file and the profile
+/// exist. A profile that is asked for by name has to exist.
+#[tracing::instrument(level = "debug")]
+pub fn load_profile(path: Option<&Path>, name: Option<&str>) -> Result<Option<Profile>> {
+    let path = match path.map(Path::to_path_buf).or_else(default_config_file) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    if !path.exists() && name.is_none() {
+        return Ok(None);
+    }
+
+    // The file is part of the arguments, so failing to read it is a usage
+    // error rather than an I/O error.
+    let contents = std::fs::read_to_string(&path).map_err(|e| {
+        ArrowError::InvalidArgumentError(format!(
+            "Can not read the config file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let mut config: Config = toml::from_str(&contents).map_err(|e| {
+        ArrowError::InvalidArgumentError(format!("Invalid config {}: {}", path.display(), e))
+    })?;
+    match name {
+        Some(name) => config.profiles.remove(name).map(Some).ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!(
+                "Profile {} not found in {}",
+                name,
+                path.display()
+            ))
+        }),
+        None => Ok(config.profiles.remove(DEFAULT_PROFILE)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_file_of_a_named_profile_is_a_usage_error() {
+        let path = std::env::temp_dir().join("missing-arrow-flight-sql-client-config.toml");
+        match load_profile(Some(&path), Some("production")) {
+            Err(ArrowError::InvalidArgumentError(message)) => {
+                assert!(message.contains(&path.display().to_string()), "{}", message)
+            }
+            other => panic!("Expected a usage error, found {:?}", other),
+        }
+        assert!(load_profile(Some(&path), None).unwrap().is_none());
+    }
+
+    #[test]
+    fn unreadable_file_is_a_usage_error() {
+        // A directory can not be read as a file.
+        let path = std::env::temp_dir();
+        assert!(matches!(
+            load_profile(Some(&path), Some("production")),
+            Err(ArrowError::InvalidArgumentError(_))
+        ));
+    }
+
+    #[test]
+    fn loads_named_profile() {
+        let path = std::env::temp_dir().join(format!(
+            "{}-arrow-flight-sql-client-config.toml",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            "[profiles.production]\nuri = \"grpc+tls://flight.example.com:443\"\n",
+        )
+        .unwrap();
+        let profile = load_profile(Some(&path), Some("production"));
+        let missing = load_profile(Some(&path), Some("staging"));
+        let default = load_profile(Some(&path), None);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            profile.unwrap().unwrap().uri.as_deref(),
+            Some("grpc+tls://flight.example.com:443")
+        );
+        assert!(matches!(missing, Err(ArrowError::InvalidArgumentError(_))));
+        assert!(default.unwrap().is_none());
+    }
+}
@@ -0,0 +1,409 @@
+use arrow::{
+    datatypes::SchemaRef,
+    error::{ArrowError, Result},
+    json::writer::record_batches_to_json_rows,
+    record_batch::RecordBatch,
+    util::display::array_value_to_string,
+};
+use clap::{ArgEnum, Args};
+use std::{io::Write, time::Instant};
+
+use crate::table::TableWriter;
+
+/// The format used to print results.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Tsv,
+    Json,
+    Ndjson,
+    Markdown,
+}
+
+/// When fields of csv and tsv output are quoted.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quoting {
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FormatArgs {
+    /// Format used to print the results
+    #[clap(long, env = "FLIGHT_SQL_FORMAT", arg_enum, default_value = "table")]
+    pub format: OutputFormat,
+    /// Delimiter between the fields of csv output
+    #[clap(long, env = "FLIGHT_SQL_DELIMITER", default_value_t = ',')]
+    pub delimiter: char,
+    /// Do not print a header with the column names in csv and tsv output
+    #[clap(long, env = "FLIGHT_SQL_NO_HEADER")]
+    pub no_header: bool,
+    /// When to quote the fields of csv and tsv output
+    #[clap(long, env = "FLIGHT_SQL_QUOTE", arg_enum, default_value = "necessary")]
+    pub quote: Quoting,
+    /// Truncate the cells of table output to this number of characters, 0 to never truncate
+    #[clap(long, env = "FLIGHT_SQL_MAX_WIDTH", default_value_t = 50)]
+    pub max_width: usize,
+    /// The moment the command started, to report the elapsed time
+    #[clap(skip = Instant::now())]
+    pub started: Instant,
+}
+
+/// Writes RecordBatches, one after the other, in an output format.
+pub trait BatchWriter {
+    /// Write the rows of the batch.
+    fn write(&mut self, batch: &RecordBatch) -> Result<()>;
+
+    /// Write whatever has to follow the last batch, and flush the output.
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Create a BatchWriter for results with the given schema in the format of
+/// the arguments.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn new_batch_writer<W: Write + 'static>(
+    args: &FormatArgs,
+    schema: SchemaRef,
+    output: W,
+) -> Result<Box<dyn BatchWriter>> {
+    Ok(match args.format {
+        OutputFormat::Table => Box::new(TableWriter::new(
+            output,
+            schema,
+            args.max_width,
+            args.started,
+        )),
+        OutputFormat::Csv => {
+            if !args.delimiter.is_ascii() {
+                return Err(ArrowError::InvalidArgumentError(format!(
+                    "The delimiter must be an ASCII character, found {}",
+                    args.delimiter
+                )));
+            }
+            Box::new(CsvWriter::new(args, args.delimiter as u8, schema, output))
+        }
+        OutputFormat::Tsv => Box::new(CsvWriter::new(args, b'\t', schema, output)),
+        OutputFormat::Json => Box::new(JsonWriter {
+            output,
+            array: true,
+            rows: 0,
+        }),
+        OutputFormat::Ndjson => Box::new(JsonWriter {
+            output,
+            array: false,
+            rows: 0,
+        }),
+        OutputFormat::Markdown => Box::new(MarkdownWriter {
+            output,
+            schema,
+            header_written: false,
+        }),
+    })
+}
+
+struct CsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+    schema: SchemaRef,
+    header: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    fn new(args: &FormatArgs, delimiter: u8, schema: SchemaRef, output: W) -> Self {
+        let quote_style = match args.quote {
+            Quoting::Necessary => csv::QuoteStyle::Necessary,
+            Quoting::Always => csv::QuoteStyle::Always,
+            Quoting::NonNumeric => csv::QuoteStyle::NonNumeric,
+            Quoting::Never => csv::QuoteStyle::Never,
+        };
+        let writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote_style(quote_style)
+            .from_writer(output);
+        CsvWriter {
+            writer,
+            schema,
+            header: !args.no_header,
+        }
+    }
+}
+
+impl<W: Write> BatchWriter for CsvWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        if self.header {
+            self.writer
+                .write_record(self.schema.fields().iter().map(|field| field.name()))
+                .map_err(csv_error_to_arrow_error)?;
+            self.header = false;
+        }
+        for row in 0..batch.num_rows() {
+            self.writer
+                .write_record(row_to_strings(batch, row)?)
+                .map_err(csv_error_to_arrow_error)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.header {
+            self.write(&RecordBatch::new_empty(self.schema.clone()))?;
+        }
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Writes the rows as JSON objects, in a single array or one per line.
+struct JsonWriter<W: Write> {
+    output: W,
+    array: bool,
+    rows: usize,
+}
+
+impl<W: Write> BatchWriter for JsonWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        for row in record_batches_to_json_rows(std::slice::from_ref(batch))? {
+            if self.array {
+                self.output
+                    .write_all(if self.rows == 0 { b"[" } else { b",\n" })?;
+            }
+            serde_json::to_writer(&mut self.output, &row)
+                .map_err(|e| ArrowError::JsonError(e.to_string()))?;
+            if !self.array {
+                self.output.write_all(b"\n")?;
+            }
+            self.rows += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.array {
+            self.output
+                .write_all(if self.rows == 0 { b"[]\n" } else { b"]\n" })?;
+        }
+        Ok(self.output.flush()?)
+    }
+}
+
+/// Writes the rows as a GitHub flavored Markdown table.
+struct MarkdownWriter<W: Write> {
+    output: W,
+    schema: SchemaRef,
+    header_written: bool,
+}
+
+impl<W: Write> MarkdownWriter<W> {
+    fn write_row<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, cells: I) -> Result<()> {
+        let cells = cells
+            .into_iter()
+            .map(|cell| escape_markdown(cell.as_ref()))
+            .collect::<Vec<_>>();
+        writeln!(self.output, "| {} |", cells.join(" | "))?;
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        if !self.header_written {
+            let schema = self.schema.clone();
+            self.write_row(schema.fields().iter().map(|field| field.name()))?;
+            writeln!(self.output, "|{}", "---|".repeat(schema.fields().len()))?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> BatchWriter for MarkdownWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.write_header()?;
+        for row in 0..batch.num_rows() {
+            self.write_row(row_to_strings(batch, row)?)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.write_header()?;
+        Ok(self.output.flush()?)
+    }
+}
+
+/// Escape the characters that would break a Markdown table cell.
+fn escape_markdown(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', "<br>")
+}
+
+/// Format all the values of a row as strings, nulls as empty strings.
+pub fn row_to_strings(batch: &RecordBatch, row: usize) -> Result<Vec<String>> {
+    batch
+        .columns()
+        .iter()
+        .map(|column| array_value_to_string(column, row))
+        .collect()
+}
+
+fn csv_error_to_arrow_error(err: csv::Error) -> ArrowError {
+    ArrowError::CsvError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{ArrayRef, DictionaryArray, Int32Array, StringArray},
+        datatypes::Int8Type,
+    };
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    /// An output that can still be read after it was moved into a writer.
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn args(format: OutputFormat) -> FormatArgs {
+        FormatArgs {
+            format,
+            delimiter: ',',
+            no_header: false,
+            quote: Quoting::Necessary,
+            max_width: 50,
+            started: Instant::now(),
+        }
+    }
+
+    /// A batch with a null in every column, a value that has to be quoted
+    /// and a dictionary column.
+    fn batch() -> RecordBatch {
+        let categories: DictionaryArray<Int8Type> =
+            vec![Some("red"), None, Some("red")].into_iter().collect();
+        RecordBatch::try_from_iter(vec![
+            (
+                "id",
+                Arc::new(Int32Array::from(vec![Some(1), Some(2), None])) as ArrayRef,
+            ),
+            (
+                "name",
+                Arc::new(StringArray::from(vec![Some("a,b"), None, Some("c|d")])) as ArrayRef,
+            ),
+            ("category", Arc::new(categories) as ArrayRef),
+        ])
+        .unwrap()
+    }
+
+    fn write(args: &FormatArgs, batches: &[RecordBatch]) -> String {
+        let output = Buffer::default();
+        let mut writer = new_batch_writer(args, batch().schema(), output.clone()).unwrap();
+        for batch in batches {
+            writer.write(batch).unwrap();
+        }
+        writer.finish().unwrap();
+        let bytes = output.0.borrow().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn writes_csv() {
+        assert_eq!(
+            write(&args(OutputFormat::Csv), &[batch(), batch().slice(0, 1)]),
+            "id,name,category\n1,\"a,b\",red\n2,,\n,c|d,red\n1,\"a,b\",red\n"
+        );
+    }
+
+    #[test]
+    fn writes_csv_with_delimiter_and_quoting() {
+        let args = FormatArgs {
+            delimiter: ';',
+            no_header: true,
+            quote: Quoting::NonNumeric,
+            ..args(OutputFormat::Csv)
+        };
+        assert_eq!(
+            write(&args, &[batch()]),
+            "1;\"a,b\";\"red\"\n2;\"\";\"\"\n\"\";\"c|d\";\"red\"\n"
+        );
+    }
+
+    #[test]
+    fn rejects_non_ascii_delimiter() {
+        let args = FormatArgs {
+            delimiter: '→',
+            ..args(OutputFormat::Csv)
+        };
+        let err = new_batch_writer(&args, batch().schema(), Buffer::default())
+            .err()
+            .unwrap();
+        assert!(matches!(err, ArrowError::InvalidArgumentError(_)));
+    }
+
+    #[test]
+    fn writes_tsv() {
+        assert_eq!(
+            write(&args(OutputFormat::Tsv), &[batch()]),
+            "id\tname\tcategory\n1\ta,b\tred\n2\t\t\n\tc|d\tred\n"
+        );
+    }
+
+    #[test]
+    fn writes_header_of_empty_csv() {
+        assert_eq!(write(&args(OutputFormat::Csv), &[]), "id,name,category\n");
+    }
+
+    #[test]
+    fn writes_json_array() {
+        assert_eq!(
+            write(&args(OutputFormat::Json), &[batch(), batch().slice(0, 1)]),
+            concat!(
+                r#"[{"category":"red","id":1,"name":"a,b"},"#,
+                "\n",
+                r#"{"id":2},"#,
+                "\n",
+                r#"{"category":"red","name":"c|d"},"#,
+                "\n",
+                r#"{"category":"red","id":1,"name":"a,b"}]"#,
+                "\n"
+            )
+        );
+        assert_eq!(write(&args(OutputFormat::Json), &[]), "[]\n");
+    }
+
+    #[test]
+    fn writes_ndjson() {
+        assert_eq!(
+            write(&args(OutputFormat::Ndjson), &[batch()]),
+            concat!(
+                r#"{"category":"red","id":1,"name":"a,b"}"#,
+                "\n",
+                r#"{"id":2}"#,
+                "\n",
+                r#"{"category":"red","name":"c|d"}"#,
+                "\n"
+            )
+        );
+        assert_eq!(write(&args(OutputFormat::Ndjson), &[]), "");
+    }
+
+    #[test]
+    fn writes_markdown() {
+        assert_eq!(
+            write(&args(OutputFormat::Markdown), &[batch()]),
+            "| id | name | category |\n|---|---|---|\n| 1 | a,b | red |\n| 2 |  |  |\n|  | c\\|d | red |\n"
+        );
+        assert_eq!(
+            write(&args(OutputFormat::Markdown), &[]),
+            "| id | name | category |\n|---|---|---|\n"
+        );
+    }
+}
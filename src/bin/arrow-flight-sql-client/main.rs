@@ -0,0 +1,917 @@
+mod completion;
+mod config;
+mod format;
+mod output;
+mod report;
+mod shell;
+mod stats;
+mod table;
+mod telemetry;
+
+use arrow::datatypes::SchemaRef;
+use arrow::error::{ArrowError, Result};
+use arrow::record_batch::RecordBatch;
+use arrow_flight_sql_client::arrow_flight_protocol::*;
+use arrow_flight_sql_client::arrow_flight_protocol_sql::*;
+use arrow_flight_sql_client::client::FlightSqlServiceClient;
+use arrow_flight_sql_client::client::*;
+use arrow_flight_sql_client::connection::{parse_seconds, ClientBuilder, TlsOptions};
+use arrow_flight_sql_client::retry::RetryPolicy;
+use arrow_flight_sql_client::sql_info::{parse_sql_info, sql_info_name_value_batch};
+use clap::{
+    ArgEnum, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueSource,
+};
+use config::load_profile;
+use format::{new_batch_writer, BatchWriter, FormatArgs, OutputFormat};
+use futures::TryStreamExt;
+use opentelemetry::global;
+use output::{new_file_writer, OutputArgs};
+use report::{report_error, ErrorFormat};
+use stats::print_stats;
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+use telemetry::{setup_telemetry, TelemetryArgs};
+use tracing::info_span;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+#[clap(propagate_version = true)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    Execute(ExecuteArgs),
+    ExecuteUpdate(ExecuteUpdateArgs),
+    GetCatalogs(GetCatalogsArgs),
+    GetTableTypes(GetTableTypesArgs),
+    GetSchemas(GetSchemasArgs),
+    GetTables(GetTablesArgs),
+    GetExportedKeys(GetExportedKeysArgs),
+    GetImportedKeys(GetImportedKeysArgs),
+    GetPrimaryKeys(GetPrimaryKeysArgs),
+    GetCrossReference(GetCrossReferenceArgs),
+    GetSqlInfo(GetSqlInfoArgs),
+    GetXdbcTypeInfo(GetXdbcTypeInfoArgs),
+    Shell(ShellArgs),
+}
+
+impl Commands {
+    fn common_mut(&mut self) -> &mut Common {
+        match self {
+            Commands::Execute(ExecuteArgs { common, .. })
+            | Commands::ExecuteUpdate(ExecuteUpdateArgs { common, .. })
+            | Commands::GetCatalogs(GetCatalogsArgs { common })
+            | Commands::GetTableTypes(GetTableTypesArgs { common })
+            | Commands::GetSchemas(GetSchemasArgs { common, .. })
+            | Commands::GetTables(GetTablesArgs { common, .. })
+            | Commands::GetExportedKeys(GetExportedKeysArgs { common, .. })
+            | Commands::GetImportedKeys(GetImportedKeysArgs { common, .. })
+            | Commands::GetPrimaryKeys(GetPrimaryKeysArgs { common, .. })
+            | Commands::GetCrossReference(GetCrossReferenceArgs { common, .. })
+            | Commands::GetSqlInfo(GetSqlInfoArgs { common, .. })
+            | Commands::GetXdbcTypeInfo(GetXdbcTypeInfoArgs { common, .. })
+            | Commands::Shell(ShellArgs { common, .. }) => common,
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+struct Common {
+    /// Profile of the configuration file to use, the default profile when not set
+    #[clap(long, env = "FLIGHT_SQL_PROFILE")]
+    profile: Option<String>,
+    /// Configuration file with the profiles, ~/.config/arrow-flight-sql-client/config.toml by default
+    #[clap(long, env = "FLIGHT_SQL_CONFIG", parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Uri of the server, such as grpc+tls://host:443 or grpc+unix:///path/to/socket, instead of --hostname and --port
+    #[clap(long, env = "FLIGHT_SQL_URI")]
+    uri: Option<String>,
+    #[clap(long, env = "FLIGHT_SQL_HOSTNAME", default_value_t = String::from("localhost"))]
+    hostname: String,
+    #[clap(
+        short,
+        long,
+        env = "FLIGHT_SQL_PORT",
+        default_value_t = 52358,
+        parse(try_from_str)
+    )]
+    port: u16,
+    /// Seconds to wait for the connection to the server to open
+    #[clap(long, env = "FLIGHT_SQL_CONNECT_TIMEOUT", parse(try_from_str = parse_seconds))]
+    connect_timeout: Option<Duration>,
+    /// Seconds to wait for the response of each call, also sent to the server as the deadline of the calls that are not streams
+    #[clap(long, env = "FLIGHT_SQL_TIMEOUT", parse(try_from_str = parse_seconds))]
+    timeout: Option<Duration>,
+    /// Seconds to wait for the next batch of a result, however long the whole result takes
+    #[clap(long, env = "FLIGHT_SQL_IDLE_TIMEOUT", parse(try_from_str = parse_seconds))]
+    idle_timeout: Option<Duration>,
+    /// Seconds between the HTTP/2 pings that check that the connection is alive
+    #[clap(long, env = "FLIGHT_SQL_HTTP2_KEEPALIVE_INTERVAL", parse(try_from_str = parse_seconds))]
+    http2_keepalive_interval: Option<Duration>,
+    /// Seconds to wait for the answer to a ping before closing the connection
+    #[clap(long, env = "FLIGHT_SQL_HTTP2_KEEPALIVE_TIMEOUT", parse(try_from_str = parse_seconds), requires = "http2-keepalive-interval")]
+    http2_keepalive_timeout: Option<Duration>,
+    /// Seconds the connection is idle before TCP keepalive probes are sent
+    #[clap(long, env = "FLIGHT_SQL_TCP_KEEPALIVE", parse(try_from_str = parse_seconds))]
+    tcp_keepalive: Option<Duration>,
+    /// Number of times metadata requests and reads of results are made at most while the server is unavailable, 1 to never retry
+    #[clap(long, env = "FLIGHT_SQL_MAX_ATTEMPTS", parse(try_from_str = parse_max_attempts))]
+    max_attempts: Option<u32>,
+    /// Seconds to wait before the first retry, doubled after every retry
+    #[clap(long, env = "FLIGHT_SQL_RETRY_BACKOFF", parse(try_from_str = parse_seconds), requires = "max-attempts")]
+    retry_backoff: Option<Duration>,
+    /// Longest number of seconds to wait before a retry
+    #[clap(long, env = "FLIGHT_SQL_RETRY_MAX_BACKOFF", parse(try_from_str = parse_seconds), requires = "max-attempts")]
+    retry_max_backoff: Option<Duration>,
+    /// Maximum number of endpoints that are read at the same time
+    #[clap(long, env = "FLIGHT_SQL_CONCURRENCY", default_value_t = 1)]
+    concurrency: usize,
+    /// Print batches as soon as they arrive from any endpoint
+    #[clap(long, env = "FLIGHT_SQL_UNORDERED")]
+    unordered: bool,
+    /// Print a summary of the calls, rows and bytes of each query on stderr
+    #[clap(long, env = "FLIGHT_SQL_STATS")]
+    stats: bool,
+    #[clap(flatten)]
+    format: FormatArgs,
+    #[clap(flatten)]
+    output: OutputArgs,
+    #[clap(flatten)]
+    telemetry: TelemetryArgs,
+    /// Format errors are printed in, json includes the kind of error and the gRPC status code
+    #[clap(
+        long,
+        env = "FLIGHT_SQL_ERROR_FORMAT",
+        arg_enum,
+        default_value = "text"
+    )]
+    error_format: ErrorFormat,
+    /// Connect with TLS, implied by the other TLS options
+    #[clap(long, env = "FLIGHT_SQL_TLS")]
+    tls: bool,
+    /// PEM file with the certificates of the authorities that signed the server certificate
+    #[clap(long, env = "FLIGHT_SQL_CA_CERT", parse(from_os_str))]
+    ca_cert: Option<PathBuf>,
+    /// PEM file with the client certificate for mutual TLS
+    #[clap(long, env = "FLIGHT_SQL_CLIENT_CERT", parse(from_os_str))]
+    client_cert: Option<PathBuf>,
+    /// PEM file with the private key of the client certificate
+    #[clap(long, env = "FLIGHT_SQL_CLIENT_KEY", parse(from_os_str))]
+    client_key: Option<PathBuf>,
+    /// Name used to verify the server certificate instead of the hostname
+    #[clap(long, env = "FLIGHT_SQL_TLS_DOMAIN_NAME")]
+    tls_domain_name: Option<String>,
+    /// Accept any server certificate, only meant for development clusters, not with --ca-cert
+    #[clap(long, env = "FLIGHT_SQL_TLS_INSECURE_SKIP_VERIFY")]
+    tls_insecure_skip_verify: bool,
+    /// Username to authenticate with using the Handshake RPC
+    #[clap(long, env = "FLIGHT_SQL_USERNAME")]
+    username: Option<String>,
+    /// Password used together with --username, which requires one
+    #[clap(long, env = "FLIGHT_SQL_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+    /// Bearer token to send with every request
+    #[clap(long, env = "FLIGHT_SQL_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Header to send with every request, as key=value
+    #[clap(long = "header", env = "FLIGHT_SQL_HEADER", parse(try_from_str = parse_header), multiple_occurrences = true)]
+    headers: Vec<(String, String)>,
+    /// The catalog of the profile, used when a command does not name one
+    #[clap(skip)]
+    catalog: Option<String>,
+    /// The schema of the profile, used when a command does not name one
+    #[clap(skip)]
+    db_schema: Option<String>,
+}
+
+/// The value of the environment variable a profile refers to.
+fn read_env(name: &str) -> Result<String> {
+    std::env::var(name).map_err(|_| {
+        ArrowError::InvalidArgumentError(format!(
+            "The environment variable {} of the profile is not set",
+            name
+        ))
+    })
+}
+
+fn parse_max_attempts(attempts: &str) -> std::result::Result<u32, String> {
+    attempts
+        .parse::<u32>()
+        .ok()
+        .filter(|attempts| *attempts > 0)
+        .ok_or_else(|| format!("expected a positive number of attempts, found {}", attempts))
+}
+
+/// The uri of the profile with another port, keeping its scheme and query
+/// parameters.
+fn uri_with_port(uri: &str, port: u16) -> Result<String> {
+    let invalid = || {
+        ArrowError::InvalidArgumentError(format!(
+            "--port can not be combined with the uri {} of the profile, give --hostname or --uri too",
+            uri
+        ))
+    };
+    let (scheme, rest) = uri.split_once("://").ok_or_else(invalid)?;
+    if scheme.to_ascii_lowercase().ends_with("unix") {
+        return Err(invalid());
+    }
+    let (authority, rest) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+    let host = match authority.find(']') {
+        Some(end) if authority.starts_with('[') => &authority[..=end],
+        _ => authority.split(':').next().unwrap_or_default(),
+    };
+    Ok(format!("{}://{}:{}{}", scheme, host, port, rest))
+}
+
+fn parse_header(header: &str) -> std::result::Result<(String, String), String> {
+    header
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .ok_or_else(|| format!("expected key=value, found {}", header))
+}
+
+impl Common {
+    /// Use the settings of the selected profile for the options that were
+    /// not given on the command line or through an environment variable.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn apply_profile(&mut self, matches: &ArgMatches) -> Result<()> {
+        let profile = match load_profile(self.config.as_deref(), self.profile.as_deref())? {
+            Some(profile) => profile,
+            None => return Ok(()),
+        };
+        let is_default = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                None | Some(ValueSource::DefaultValue)
+            )
+        };
+
+        // An address on the command line replaces the uri of the profile, which
+        // may also be a Unix socket. A port alone only replaces the port of the
+        // uri, so that its scheme and TLS parameters are kept.
+        if let (None, Some(uri), true) = (&self.uri, &profile.uri, is_default("hostname")) {
+            self.uri = Some(match is_default("port") {
+                true => uri.clone(),
+                false => uri_with_port(uri, self.port)?,
+            });
+        }
+        if let (true, Some(hostname)) = (is_default("hostname"), profile.hostname) {
+            self.hostname = hostname;
+        }
+        if let (true, Some(port)) = (is_default("port"), profile.port) {
+            self.port = port;
+        }
+        self.telemetry.otlp_endpoint = self
+            .telemetry
+            .otlp_endpoint
+            .take()
+            .or(profile.otlp_endpoint);
+        if let (true, Some(format)) = (is_default("format"), profile.format) {
+            self.format.format = OutputFormat::from_str(&format, true).map_err(|_| {
+                ArrowError::InvalidArgumentError(format!("Invalid format {} in profile", format))
+            })?;
+        }
+        if let (true, Some(tls)) = (is_default("tls"), profile.tls) {
+            self.tls = tls;
+        }
+        if let (true, Some(tls_insecure_skip_verify)) = (
+            is_default("tls-insecure-skip-verify"),
+            profile.tls_insecure_skip_verify,
+        ) {
+            self.tls_insecure_skip_verify = tls_insecure_skip_verify;
+        }
+        self.ca_cert = self.ca_cert.take().or(profile.ca_cert);
+        self.client_cert = self.client_cert.take().or(profile.client_cert);
+        self.client_key = self.client_key.take().or(profile.client_key);
+        self.tls_domain_name = self.tls_domain_name.take().or(profile.tls_domain_name);
+        self.username = self.username.take().or(profile.username);
+        if let (None, Some(password_env)) = (&self.password, profile.password_env) {
+            self.password = Some(read_env(&password_env)?);
+        }
+        if let (None, Some(token_env)) = (&self.token, profile.token_env) {
+            self.token = Some(read_env(&token_env)?);
+        }
+        // The headers of the command line come last, so that they replace those
+        // of the profile with the same key.
+        self.headers = profile
+            .headers
+            .into_iter()
+            .chain(std::mem::take(&mut self.headers))
+            .collect();
+        self.catalog = profile.catalog;
+        self.db_schema = profile.db_schema;
+        Ok(())
+    }
+
+    /// The username and password of the Handshake, if any. A username
+    /// without a password is rejected rather than sent with an empty one.
+    fn credentials(&self) -> Result<Option<(&str, &str)>> {
+        match (&self.username, &self.password) {
+            (Some(username), Some(password)) => Ok(Some((username, password))),
+            (Some(_), None) => Err(ArrowError::InvalidArgumentError(
+                "--username requires --password, FLIGHT_SQL_PASSWORD or a profile password-env"
+                    .to_string(),
+            )),
+            (None, _) => Ok(None),
+        }
+    }
+
+    fn tls_options(&self) -> Result<Option<TlsOptions>> {
+        if !(self.tls
+            || self.ca_cert.is_some()
+            || self.client_cert.is_some()
+            || self.tls_domain_name.is_some()
+            || self.tls_insecure_skip_verify)
+        {
+            return Ok(None);
+        }
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            return Err(ArrowError::InvalidArgumentError(
+                "--client-cert and --client-key must be given together".to_string(),
+            ));
+        }
+        if self.tls_insecure_skip_verify && self.ca_cert.is_some() {
+            return Err(ArrowError::InvalidArgumentError(
+                "--ca-cert can not be used with --tls-insecure-skip-verify, which accepts any server certificate".to_string(),
+            ));
+        }
+        let tls = TlsOptions::from_pem_files(
+            self.ca_cert.as_deref(),
+            self.client_cert.as_deref(),
+            self.client_key.as_deref(),
+        )?;
+        Ok(Some(TlsOptions {
+            domain_name: self.tls_domain_name.clone(),
+            insecure_skip_verify: self.tls_insecure_skip_verify,
+            ..tls
+        }))
+    }
+}
+
+#[derive(Args, Debug)]
+struct ExecuteArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(short, long, env = "FLIGHT_SQL_QUERY")]
+    query: String,
+}
+
+#[derive(Args, Debug)]
+struct ExecuteUpdateArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(short, long, env = "FLIGHT_SQL_QUERY")]
+    query: String,
+}
+
+#[derive(Args, Debug)]
+struct GetCatalogsArgs {
+    #[clap(flatten)]
+    common: Common,
+}
+
+#[derive(Args, Debug)]
+struct GetTableTypesArgs {
+    #[clap(flatten)]
+    common: Common,
+}
+
+#[derive(Args, Debug)]
+struct GetSchemasArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(short, long, env = "FLIGHT_SQL_CATALOG")]
+    catalog: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_DB_SCHEMA_FILTER_PATTERN")]
+    db_schema_filter_pattern: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct GetTablesArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(short, long, env = "FLIGHT_SQL_CATALOG")]
+    catalog: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_DB_SCHEMA_FILTER_PATTERN")]
+    db_schema_filter_pattern: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_TABLE_NAME_FILTER_PATTERN")]
+    table_name_filter_pattern: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_INCLUDE_SCHEMA")]
+    include_schema: bool,
+}
+
+#[derive(Args, Debug)]
+struct GetExportedKeysArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(short, long, env = "FLIGHT_SQL_CATALOG")]
+    catalog: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_DB_SCHEMA")]
+    db_schema: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_TABLE")]
+    table: String,
+}
+
+#[derive(Args, Debug)]
+struct GetImportedKeysArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(short, long, env = "FLIGHT_SQL_CATALOG")]
+    catalog: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_DB_SCHEMA")]
+    db_schema: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_TABLE")]
+    table: String,
+}
+
+#[derive(Args, Debug)]
+struct GetPrimaryKeysArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(short, long, env = "FLIGHT_SQL_CATALOG")]
+    catalog: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_DB_SCHEMA")]
+    db_schema: Option<String>,
+    #[clap(short, long, env = "FLIGHT_SQL_TABLE")]
+    table: String,
+}
+
+#[derive(Args, Debug)]
+struct GetCrossReferenceArgs {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(long, env = "FLIGHT_SQL_PK_CATALOG")]
+    pk_catalog: Option<String>,
+    #[clap(long, env = "FLIGHT_SQL_PK_DB_SCHEMA")]
+    pk_db_schema: Option<String>,
+    #[clap(long, env = "FLIGHT_SQL_PK_TABLE")]
+    pk_table: String,
+    #[clap(long, env = "FLIGHT_SQL_FK_CATALOG")]
+    fk_catalog: Option<String>,
+    #[clap(long, env = "FLIGHT_SQL_FK_DB_SCHEMA")]
+    fk_db_schema: Option<String>,
+    #[clap(long, env = "FLIGHT_SQL_FK_TABLE")]
+    fk_table: String,
+}
+
+#[derive(Args, Debug)]
+struct GetSqlInfoArgs {
+    #[clap(flatten)]
+    common: Common,
+    /// SqlInfo names, such as FLIGHT_SQL_SERVER_NAME or server_name, or numbers.
+    /// All SqlInfos are requested when none are given
+    #[clap(env = "FLIGHT_SQL_INFO", use_value_delimiter = true)]
+    info: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct GetXdbcTypeInfoArgs {
+    #[clap(flatten)]
+    common: Common,
+    /// Only request the type info of this data type
+    #[clap(short, long, env = "FLIGHT_SQL_DATA_TYPE", allow_hyphen_values = true)]
+    data_type: Option<i32>,
+}
+
+#[derive(Args, Debug)]
+struct ShellArgs {
+    #[clap(flatten)]
+    common: Common,
+    /// File the history of the shell is kept in, ~/.arrow_flight_sql_client_history by default
+    #[clap(long, env = "FLIGHT_SQL_HISTORY_FILE", parse(from_os_str))]
+    history_file: Option<PathBuf>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn new_client(common: &Common) -> Result<FlightSqlServiceClient> {
+    let mut builder = match &common.uri {
+        Some(uri) => ClientBuilder::from_uri(uri)?,
+        None => ClientBuilder::new(&common.hostname, common.port),
+    };
+    if let Some(tls) = common.tls_options()? {
+        builder = builder.with_tls(tls);
+    }
+    if let Some(timeout) = common.connect_timeout {
+        builder = builder.with_connect_timeout(timeout);
+    }
+    if let Some(timeout) = common.timeout {
+        builder = builder.with_timeout(timeout);
+    }
+    if let Some(interval) = common.http2_keepalive_interval {
+        builder = builder.with_http2_keepalive(interval, common.http2_keepalive_timeout);
+    }
+    if let Some(idle) = common.tcp_keepalive {
+        builder = builder.with_tcp_keepalive(idle);
+    }
+    if let Some(max_attempts) = common.max_attempts {
+        let default = RetryPolicy::default();
+        builder = builder.with_retry_policy(RetryPolicy {
+            max_attempts,
+            initial_backoff: common.retry_backoff.unwrap_or(default.initial_backoff),
+            max_backoff: common.retry_max_backoff.unwrap_or(default.max_backoff),
+            ..default
+        });
+    }
+    for (key, value) in &common.headers {
+        builder = builder.with_header(key, value);
+    }
+    if let Some(token) = &common.token {
+        builder = builder.with_token(token);
+    }
+    if let Some((username, password)) = common.credentials()? {
+        builder = builder.with_basic_auth(username, password);
+    }
+    Ok(builder.connect().await?)
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_and_print(
+    client: FlightSqlServiceClient,
+    fi: FlightInfo,
+    common: &Common,
+) -> Result<()> {
+    let options = DoGetOptions {
+        concurrency: common.concurrency,
+        ordered: !common.unordered,
+        idle_timeout: common.idle_timeout,
+    };
+    let schema = SchemaRef::new(arrow_schema_from_flight_info(&fi)?);
+    let writer = new_writer(common, schema)?;
+    let record_batch_stream = client.do_get_endpoints(&fi, &options).await?;
+    print_record_batch_stream(record_batch_stream, writer).await?;
+    print_stats(&client, common);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let error_format = cli.command.common_mut().error_format;
+    let result = run(cli, &matches).await;
+
+    global::shutdown_tracer_provider();
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => report_error(&err, error_format),
+    }
+}
+
+async fn run(mut cli: Cli, matches: &ArgMatches) -> Result<()> {
+    if let Some((_, matches)) = matches.subcommand() {
+        cli.command.common_mut().apply_profile(matches)?;
+    }
+    let _baggage = setup_telemetry(&cli.command.common_mut().telemetry)?;
+
+    match &cli.command {
+        Commands::Execute(ExecuteArgs { common, query }) => {
+            let _parent_span = info_span!("execute command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client.execute(query.to_string()).await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::ExecuteUpdate(ExecuteUpdateArgs { common, query }) => {
+            let _parent_span = info_span!("execute update command").entered();
+
+            let client = new_client(common).await?;
+            let record_count = client.execute_update(query.to_string()).await?;
+            println!("Updated {} records.", record_count);
+            print_stats(&client, common);
+            Ok(())
+        }
+        Commands::GetCatalogs(GetCatalogsArgs { common }) => {
+            let _parent_span = info_span!("get catalogs command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client.get_catalogs().await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetTableTypes(GetTableTypesArgs { common }) => {
+            let _parent_span = info_span!("get table types command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client.get_table_types().await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetSchemas(GetSchemasArgs {
+            common,
+            catalog,
+            db_schema_filter_pattern: schema,
+        }) => {
+            let _parent_span = info_span!("get schemas command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client
+                .get_db_schemas(CommandGetDbSchemas {
+                    catalog: catalog.clone().or_else(|| common.catalog.clone()),
+                    db_schema_filter_pattern: schema.as_deref().map(|x| x.to_string()),
+                })
+                .await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetTables(GetTablesArgs {
+            common,
+            catalog,
+            db_schema_filter_pattern,
+            table_name_filter_pattern,
+            include_schema,
+        }) => {
+            let _parent_span = info_span!("get tables command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client
+                .get_tables(CommandGetTables {
+                    catalog: catalog.clone().or_else(|| common.catalog.clone()),
+                    db_schema_filter_pattern: db_schema_filter_pattern
+                        .clone()
+                        .or_else(|| common.db_schema.clone()),
+                    table_name_filter_pattern: table_name_filter_pattern
+                        .as_deref()
+                        .map(|x| x.to_string()),
+                    table_types: vec![],
+                    include_schema: *include_schema,
+                })
+                .await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetExportedKeys(GetExportedKeysArgs {
+            common,
+            catalog,
+            db_schema,
+            table,
+        }) => {
+            let _parent_span = info_span!("get exported keys command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client
+                .get_exported_keys(CommandGetExportedKeys {
+                    catalog: catalog.clone().or_else(|| common.catalog.clone()),
+                    db_schema: db_schema.clone().or_else(|| common.db_schema.clone()),
+                    table: table.to_string(),
+                })
+                .await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetImportedKeys(GetImportedKeysArgs {
+            common,
+            catalog,
+            db_schema,
+            table,
+        }) => {
+            let _parent_span = info_span!("get imported keys command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client
+                .get_imported_keys(CommandGetImportedKeys {
+                    catalog: catalog.clone().or_else(|| common.catalog.clone()),
+                    db_schema: db_schema.clone().or_else(|| common.db_schema.clone()),
+                    table: table.to_string(),
+                })
+                .await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetPrimaryKeys(GetPrimaryKeysArgs {
+            common,
+            catalog,
+            db_schema,
+            table,
+        }) => {
+            let _parent_span = info_span!("get primary keys command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client
+                .get_primary_keys(CommandGetPrimaryKeys {
+                    catalog: catalog.clone().or_else(|| common.catalog.clone()),
+                    db_schema: db_schema.clone().or_else(|| common.db_schema.clone()),
+                    table: table.to_string(),
+                })
+                .await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetCrossReference(GetCrossReferenceArgs {
+            common,
+            pk_catalog,
+            pk_db_schema,
+            pk_table,
+            fk_catalog,
+            fk_db_schema,
+            fk_table,
+        }) => {
+            let _parent_span = info_span!("get cross reference command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client
+                .get_cross_reference(CommandGetCrossReference {
+                    pk_catalog: pk_catalog.clone().or_else(|| common.catalog.clone()),
+                    pk_db_schema: pk_db_schema.clone().or_else(|| common.db_schema.clone()),
+                    pk_table: pk_table.to_string(),
+                    fk_catalog: fk_catalog.clone().or_else(|| common.catalog.clone()),
+                    fk_db_schema: fk_db_schema.clone().or_else(|| common.db_schema.clone()),
+                    fk_table: fk_table.to_string(),
+                })
+                .await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::GetSqlInfo(GetSqlInfoArgs { common, info }) => {
+            let _parent_span = info_span!("get sql info command").entered();
+
+            let sql_infos = info
+                .iter()
+                .map(|name| parse_sql_info(name))
+                .collect::<Result<Vec<_>>>()?;
+            let client = new_client(common).await?;
+            let infos = client.get_sql_info_values(sql_infos).await?;
+            print_batch(sql_info_name_value_batch(&infos)?, common)?;
+            print_stats(&client, common);
+            Ok(())
+        }
+        Commands::GetXdbcTypeInfo(GetXdbcTypeInfoArgs { common, data_type }) => {
+            let _parent_span = info_span!("get xdbc type info command").entered();
+
+            let client = new_client(common).await?;
+            let fi = client
+                .get_xdbc_type_info(CommandGetXdbcTypeInfo {
+                    data_type: *data_type,
+                })
+                .await?;
+            get_and_print(client, fi, common).await
+        }
+        Commands::Shell(ShellArgs {
+            common,
+            history_file,
+        }) => {
+            let client = new_client(common).await?;
+            shell::run_shell(client, common, history_file.clone()).await
+        }
+    }?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn print_record_batch_stream(
+    mut record_batch_stream: RecordBatchStream,
+    mut writer: Box<dyn BatchWriter>,
+) -> Result<()> {
+    while let Some(record_batch) = record_batch_stream.try_next().await? {
+        writer.write(&record_batch)?;
+    }
+    writer.finish()
+}
+
+/// Create the writer that prints results, or writes them to the output file.
+#[tracing::instrument(skip_all)]
+fn new_writer(common: &Common, schema: SchemaRef) -> Result<Box<dyn BatchWriter>> {
+    match &common.output.output {
+        Some(path) => new_file_writer(&common.output, path, schema),
+        None => new_batch_writer(&common.format, schema, std::io::stdout()),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+fn print_batch(record_batch: RecordBatch, common: &Common) -> Result<()> {
+    let mut writer = new_writer(common, record_batch.schema())?;
+    writer.write(&record_batch)?;
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[profiles.uri]
+uri = "grpc+unix:///run/flight.sock"
+
+[profiles.tls]
+uri = "grpc+tls://[2001:db8::1]:443/?tls_domain_name=flight.internal"
+hostname = "flight.example.com"
+
+[profiles.hostname]
+hostname = "flight.example.com"
+port = 443
+"#;
+
+    /// Parse the arguments of get-catalogs, with a config file written
+    /// under the name, and apply the profile.
+    fn profile_options(name: &str, args: &[&str]) -> Result<Common> {
+        let path = std::env::temp_dir().join(format!("{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, CONFIG).unwrap();
+        let path = path.to_str().unwrap().to_string();
+        let matches = Cli::command()
+            .try_get_matches_from(
+                ["client", "get-catalogs", "--config", &path]
+                    .iter()
+                    .chain(args),
+            )
+            .unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        let result = cli
+            .command
+            .common_mut()
+            .apply_profile(matches.subcommand().unwrap().1);
+        std::fs::remove_file(&path).unwrap();
+        result.map(|()| cli.command.common_mut().clone())
+    }
+
+    #[test]
+    fn username_requires_a_password() {
+        let common = profile_options("no-password", &["--username", "analyst"]).unwrap();
+        if std::env::var_os("FLIGHT_SQL_PASSWORD").is_none() {
+            assert!(matches!(
+                common.credentials(),
+                Err(ArrowError::InvalidArgumentError(_))
+            ));
+        }
+
+        let common = profile_options(
+            "password",
+            &["--username", "analyst", "--password", "secret"],
+        )
+        .unwrap();
+        assert_eq!(common.credentials().unwrap(), Some(("analyst", "secret")));
+    }
+
+    #[test]
+    fn uses_uri_of_the_profile_without_an_address() {
+        let common = profile_options("profile-uri", &["--profile", "uri"]).unwrap();
+        assert_eq!(common.uri.as_deref(), Some("grpc+unix:///run/flight.sock"));
+    }
+
+    #[test]
+    fn address_on_the_command_line_replaces_uri_of_the_profile() {
+        let common = profile_options(
+            "explicit-uri",
+            &["--profile", "uri", "--uri", "grpc+tcp://other:1234"],
+        )
+        .unwrap();
+        assert_eq!(common.uri.as_deref(), Some("grpc+tcp://other:1234"));
+
+        let common = profile_options(
+            "explicit-hostname",
+            &["--profile", "uri", "--hostname", "other"],
+        )
+        .unwrap();
+        assert_eq!(common.uri, None);
+        assert_eq!(common.hostname, "other");
+        assert_eq!(common.port, 52358);
+    }
+
+    #[test]
+    fn port_on_the_command_line_keeps_hostname_of_the_profile() {
+        let common = profile_options(
+            "explicit-port",
+            &["--profile", "hostname", "--port", "8443"],
+        )
+        .unwrap();
+        assert_eq!(common.uri, None);
+        assert_eq!(common.hostname, "flight.example.com");
+        assert_eq!(common.port, 8443);
+    }
+
+    #[test]
+    fn port_on_the_command_line_replaces_port_of_the_uri_of_the_profile() {
+        let common = profile_options("tls-port", &["--profile", "tls", "--port", "8443"]).unwrap();
+        let uri = common.uri.unwrap();
+        assert_eq!(
+            uri,
+            "grpc+tls://[2001:db8::1]:8443/?tls_domain_name=flight.internal"
+        );
+        let builder = ClientBuilder::from_uri(&uri).unwrap();
+        assert_eq!(
+            builder.tls().unwrap().domain_name.as_deref(),
+            Some("flight.internal")
+        );
+    }
+
+    #[test]
+    fn port_on_the_command_line_conflicts_with_unix_socket_of_the_profile() {
+        assert!(matches!(
+            profile_options("conflicting-port", &["--profile", "uri", "--port", "8443"]),
+            Err(ArrowError::InvalidArgumentError(_))
+        ));
+    }
+
+    #[test]
+    fn replaces_port_of_uri() {
+        for (uri, expected) in [
+            (
+                "grpc+tls://flight.example.com",
+                "grpc+tls://flight.example.com:1",
+            ),
+            (
+                "grpc://flight.example.com:443",
+                "grpc://flight.example.com:1",
+            ),
+            ("https://[::1]:443?timeout=5", "https://[::1]:1?timeout=5"),
+            ("grpc+tcp://[::1]", "grpc+tcp://[::1]:1"),
+        ] {
+            assert_eq!(uri_with_port(uri, 1).unwrap(), expected);
+        }
+        assert!(uri_with_port("grpc+unix:///run/flight.sock", 1).is_err());
+        assert!(uri_with_port("flight.example.com", 1).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use arrow::{
+    datatypes::SchemaRef,
+    error::{ArrowError, Result},
+    ipc::writer::{FileWriter, StreamWriter},
+    record_batch::RecordBatch,
+};
+use clap::{ArgEnum, Args};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use crate::format::BatchWriter;
+
+/// The format of a file the results are written to.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Parquet,
+    ArrowFile,
+    ArrowStream,
+}
+
+/// The codec used to compress the pages of a Parquet file.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParquetCompression {
+    Uncompressed,
+    Snappy,
+    Gzip,
+    Lz4,
+    Zstd,
+    Brotli,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// Write the results to this file instead of printing them
+    #[clap(short, long, env = "FLIGHT_SQL_OUTPUT", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    /// Format of the output file, derived from its extension (.parquet, .arrow or .arrows) when not set
+    #[clap(long, env = "FLIGHT_SQL_OUTPUT_FORMAT", arg_enum)]
+    pub output_format: Option<FileFormat>,
+    /// Codec used to compress Parquet files
+    #[clap(
+        long,
+        env = "FLIGHT_SQL_COMPRESSION",
+        arg_enum,
+        default_value = "snappy"
+    )]
+    pub compression: ParquetCompression,
+    /// Maximum number of rows in a row group of Parquet files
+    #[clap(long, env = "FLIGHT_SQL_ROW_GROUP_SIZE", default_value_t = 1024 * 1024)]
+    pub row_group_size: usize,
+}
+
+/// Create a BatchWriter that writes results with the given schema to the
+/// file in the arguments.
+///
+/// The results are written to a temporary file next to it, which only
+/// replaces the file once finished, so that a failed query leaves no
+/// truncated file behind.
+#[tracing::instrument(level = "debug", skip_all, fields(path = ?path))]
+pub fn new_file_writer(
+    args: &OutputArgs,
+    path: &Path,
+    schema: SchemaRef,
+) -> Result<Box<dyn BatchWriter>> {
+    let format = match args.output_format {
+        Some(format) => format,
+        None => file_format_from_extension(path)?,
+    };
+    let temp_path = temp_path(path)?;
+    let file = BufWriter::new(File::create(&temp_path)?);
+    let writer: Box<dyn BatchWriter> = match format {
+        FileFormat::Parquet => {
+            let compression = match args.compression {
+                ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+                ParquetCompression::Snappy => Compression::SNAPPY,
+                ParquetCompression::Gzip => Compression::GZIP,
+                ParquetCompression::Lz4 => Compression::LZ4,
+                ParquetCompression::Zstd => Compression::ZSTD,
+                ParquetCompression::Brotli => Compression::BROTLI,
+            };
+            let properties = WriterProperties::builder()
+                .set_compression(compression)
+                .set_max_row_group_size(args.row_group_size.max(1))
+                .build();
+            let writer = ArrowWriter::try_new(file, schema, Some(properties))
+                .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+            Box::new(ParquetFileWriter {
+                writer: Some(writer),
+            })
+        }
+        FileFormat::ArrowFile => Box::new(FileWriter::try_new(file, &schema)?),
+        FileFormat::ArrowStream => Box::new(StreamWriter::try_new(file, &schema)?),
+    };
+    Ok(Box::new(TempFileWriter {
+        writer: Some(writer),
+        temp_path,
+        path: path.to_path_buf(),
+    }))
+}
+
+/// The hidden file the results are written to before they replace the file
+/// at the path.
+fn temp_path(path: &Path) -> Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| {
+        ArrowError::InvalidArgumentError(format!("{} is not a file", path.display()))
+    })?;
+    Ok(path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    )))
+}
+
+#[tracing::instrument(level = "debug")]
+fn file_format_from_extension(path: &Path) -> Result<FileFormat> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("parquet") | Some("pq") => Ok(FileFormat::Parquet),
+        Some("arrow") | Some("feather") | Some("ipc") => Ok(FileFormat::ArrowFile),
+        Some("arrows") => Ok(FileFormat::ArrowStream),
+        _ => Err(ArrowError::InvalidArgumentError(format!(
+            "Can not derive the format of {} from its extension, use --output-format",
+            path.display()
+        ))),
+    }
+}
+
+struct ParquetFileWriter {
+    writer: Option<ArrowWriter<BufWriter<File>>>,
+}
+
+impl BatchWriter for ParquetFileWriter {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.writer
+            .as_mut()
+            .ok_or_else(|| ArrowError::IoError("Parquet file already closed".to_string()))?
+            .write(batch)
+            .map_err(|e| ArrowError::ExternalError(Box::new(e)))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer
+                .close()
+                .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Write to a temporary file, renamed to the path once finished and removed
+/// when dropped before.
+struct TempFileWriter {
+    writer: Option<Box<dyn BatchWriter>>,
+    temp_path: PathBuf,
+    path: PathBuf,
+}
+
+impl BatchWriter for TempFileWriter {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.writer
+            .as_mut()
+            .ok_or_else(|| ArrowError::IoError("Output file already closed".to_string()))?
+            .write(batch)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.finish()?;
+            // Close the file before renaming it.
+            drop(writer);
+            std::fs::rename(&self.temp_path, &self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TempFileWriter {
+    fn drop(&mut self) {
+        // Close the file first, and leave nothing behind when it was not
+        // renamed.
+        drop(self.writer.take());
+        if self.temp_path.exists() {
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+impl BatchWriter for FileWriter<BufWriter<File>> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        FileWriter::write(self, batch)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        FileWriter::finish(self)
+    }
+}
+
+impl BatchWriter for StreamWriter<BufWriter<File>> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        StreamWriter::write(self, batch)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        StreamWriter::finish(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{ArrayRef, Int64Array, StringArray},
+        ipc::reader::{FileReader, StreamReader},
+    };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::sync::Arc;
+
+    fn args(output_format: Option<FileFormat>) -> OutputArgs {
+        OutputArgs {
+            output: None,
+            output_format,
+            compression: ParquetCompression::Zstd,
+            row_group_size: 2,
+        }
+    }
+
+    fn batch() -> RecordBatch {
+        RecordBatch::try_from_iter(vec![
+            (
+                "id",
+                Arc::new(Int64Array::from(vec![Some(1), None, Some(3)])) as ArrayRef,
+            ),
+            (
+                "name",
+                Arc::new(StringArray::from(vec![Some("a"), Some("b"), None])) as ArrayRef,
+            ),
+        ])
+        .unwrap()
+    }
+
+    /// Write two batches to a file with the name in the temporary directory,
+    /// and return its path.
+    fn write(args: &OutputArgs, name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        let mut writer = new_file_writer(args, &path, batch().schema()).unwrap();
+        writer.write(&batch()).unwrap();
+        writer.write(&batch()).unwrap();
+        writer.finish().unwrap();
+        path
+    }
+
+    fn assert_batches(batches: Vec<RecordBatch>) {
+        let expected =
+            arrow::compute::concat_batches(&batch().schema(), &[batch(), batch()]).unwrap();
+        let batches = arrow::compute::concat_batches(&batch().schema(), &batches).unwrap();
+        assert_eq!(batches, expected);
+    }
+
+    #[test]
+    fn derives_format_from_extension() {
+        for (path, format) in [
+            ("results.parquet", FileFormat::Parquet),
+            ("results.pq", FileFormat::Parquet),
+            ("results.arrow", FileFormat::ArrowFile),
+            ("results.feather", FileFormat::ArrowFile),
+            ("results.ipc", FileFormat::ArrowFile),
+            ("results.arrows", FileFormat::ArrowStream),
+        ] {
+            assert_eq!(file_format_from_extension(Path::new(path)).unwrap(), format);
+        }
+        for path in ["results.csv", "results"] {
+            assert!(matches!(
+                file_format_from_extension(Path::new(path)),
+                Err(ArrowError::InvalidArgumentError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn writes_parquet() {
+        let path = write(&args(None), "results.parquet");
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        let metadata = builder.metadata().clone();
+        let batches = builder
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_batches(batches);
+        assert_eq!(metadata.num_row_groups(), 3);
+        assert_eq!(
+            metadata.row_group(0).column(0).compression(),
+            Compression::ZSTD
+        );
+    }
+
+    #[test]
+    fn writes_arrow_file() {
+        let path = write(&args(None), "results.arrow");
+        let batches = FileReader::try_new(File::open(&path).unwrap(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_batches(batches);
+    }
+
+    #[test]
+    fn unfinished_file_is_removed() {
+        let path = std::env::temp_dir().join(format!("{}-unfinished.arrow", std::process::id()));
+        std::fs::write(&path, b"previous results").unwrap();
+        let mut writer = new_file_writer(&args(None), &path, batch().schema()).unwrap();
+        writer.write(&batch()).unwrap();
+        assert!(temp_path(&path).unwrap().exists());
+        drop(writer);
+        assert!(!temp_path(&path).unwrap().exists());
+        assert_eq!(std::fs::read(&path).unwrap(), b"previous results");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn writes_format_of_the_arguments() {
+        let path = write(&args(Some(FileFormat::ArrowStream)), "stream.arrow");
+        let batches = StreamReader::try_new(File::open(&path).unwrap(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_batches(batches);
+    }
+}
@@ -0,0 +1,210 @@
+use arrow::error::ArrowError;
+use arrow_flight_sql_client::error::FlightSqlError;
+use clap::ArgEnum;
+use std::process::ExitCode;
+use tonic::Code;
+
+/// The format errors are printed in.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+/// The category of an error, which decides the exit code of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Any other error, such as failing to write the results.
+    Other,
+    /// The arguments or the configuration are invalid.
+    Usage,
+    /// The server could not be reached, or stopped sending data.
+    Connection,
+    /// The server rejected the credentials or denied access.
+    Auth,
+    /// The server failed to run the request, or returned invalid data.
+    Query,
+}
+
+impl ErrorKind {
+    /// Classify an error based on the FlightSqlError it was converted from,
+    /// and the gRPC status code returned by the server.
+    pub fn of(err: &ArrowError) -> Self {
+        match FlightSqlError::downcast_ref(err) {
+            Some(FlightSqlError::Status(status)) => match status.code() {
+                Code::Unavailable => ErrorKind::Connection,
+                Code::Unauthenticated | Code::PermissionDenied => ErrorKind::Auth,
+                _ => ErrorKind::Query,
+            },
+            Some(FlightSqlError::Transport(_)) | Some(FlightSqlError::Timeout(_)) => {
+                ErrorKind::Connection
+            }
+            Some(FlightSqlError::Decode(_)) | Some(FlightSqlError::Protocol(_)) => ErrorKind::Query,
+            // Local files are only read for the TLS options.
+            Some(FlightSqlError::InvalidArgument(_)) | Some(FlightSqlError::Io(_)) => {
+                ErrorKind::Usage
+            }
+            Some(FlightSqlError::Arrow(err)) => ErrorKind::of(err),
+            None => match err {
+                ArrowError::InvalidArgumentError(_) => ErrorKind::Usage,
+                ArrowError::IoError(_)
+                | ArrowError::CsvError(_)
+                | ArrowError::JsonError(_)
+                | ArrowError::ExternalError(_) => ErrorKind::Other,
+                _ => ErrorKind::Query,
+            },
+        }
+    }
+
+    /// The exit code, where 2 matches the code of the usage errors that are
+    /// reported while parsing the arguments.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::Connection => 3,
+            ErrorKind::Auth => 4,
+            ErrorKind::Query => 5,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Other => "other",
+            ErrorKind::Usage => "usage",
+            ErrorKind::Connection => "connection",
+            ErrorKind::Auth => "auth",
+            ErrorKind::Query => "query",
+        }
+    }
+}
+
+/// Print the error on stderr in the given format, and return the exit code
+/// for its kind.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn report_error(err: &ArrowError, format: ErrorFormat) -> ExitCode {
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {}", message(err)),
+        ErrorFormat::Json => eprintln!("{}", error_json(err)),
+    }
+    ExitCode::from(ErrorKind::of(err).exit_code())
+}
+
+/// The message of the error, without the wrapping of the ArrowError.
+fn message(err: &ArrowError) -> String {
+    match FlightSqlError::downcast_ref(err) {
+        Some(err) => err.to_string(),
+        None => err.to_string(),
+    }
+}
+
+/// The error as printed with `--error-format json`.
+fn error_json(err: &ArrowError) -> serde_json::Value {
+    let kind = ErrorKind::of(err);
+    let code = FlightSqlError::downcast_ref(err).and_then(FlightSqlError::code);
+    serde_json::json!({
+        "kind": kind.name(),
+        "message": message(err),
+        "grpc_code": code.map(|code| code as i32),
+        "grpc_status": code.map(|code| format!("{:?}", code)),
+        "exit_code": kind.exit_code(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Status;
+
+    fn status(code: Code) -> ArrowError {
+        FlightSqlError::from(Status::new(code, "failed")).into()
+    }
+
+    #[test]
+    fn maps_each_kind_to_its_exit_code() {
+        let cases = [
+            (status(Code::Unavailable), ErrorKind::Connection, 3),
+            (status(Code::Unauthenticated), ErrorKind::Auth, 4),
+            (status(Code::PermissionDenied), ErrorKind::Auth, 4),
+            (status(Code::InvalidArgument), ErrorKind::Query, 5),
+            (status(Code::Internal), ErrorKind::Query, 5),
+            (
+                FlightSqlError::Timeout("idle".to_string()).into(),
+                ErrorKind::Connection,
+                3,
+            ),
+            (
+                FlightSqlError::Decode("bad message".into()).into(),
+                ErrorKind::Query,
+                5,
+            ),
+            (
+                FlightSqlError::Protocol("no token".to_string()).into(),
+                ErrorKind::Query,
+                5,
+            ),
+            (
+                FlightSqlError::InvalidArgument("bad uri".to_string()).into(),
+                ErrorKind::Usage,
+                2,
+            ),
+            (
+                FlightSqlError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "no file"))
+                    .into(),
+                ErrorKind::Usage,
+                2,
+            ),
+            (
+                ArrowError::InvalidArgumentError("bad format".to_string()),
+                ErrorKind::Usage,
+                2,
+            ),
+            (
+                ArrowError::IoError("broken pipe".to_string()),
+                ErrorKind::Other,
+                1,
+            ),
+            (
+                ArrowError::ComputeError("overflow".to_string()),
+                ErrorKind::Query,
+                5,
+            ),
+        ];
+        for (err, kind, exit_code) in cases {
+            assert_eq!(ErrorKind::of(&err), kind, "{}", err);
+            assert_eq!(kind.exit_code(), exit_code);
+            assert_eq!(
+                report_error(&err, ErrorFormat::Text),
+                ExitCode::from(exit_code)
+            );
+        }
+    }
+
+    #[test]
+    fn prints_status_as_json() {
+        assert_eq!(
+            error_json(&status(Code::PermissionDenied)),
+            serde_json::json!({
+                "kind": "auth",
+                "message": "Server returned PermissionDenied: failed",
+                "grpc_code": 7,
+                "grpc_status": "PermissionDenied",
+                "exit_code": 4,
+            })
+        );
+    }
+
+    #[test]
+    fn prints_local_error_as_json() {
+        assert_eq!(
+            error_json(&ArrowError::InvalidArgumentError("bad format".to_string())),
+            serde_json::json!({
+                "kind": "usage",
+                "message": "Invalid argument error: bad format",
+                "grpc_code": null,
+                "grpc_status": null,
+                "exit_code": 2,
+            })
+        );
+    }
+}
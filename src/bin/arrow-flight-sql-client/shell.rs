@@ -0,0 +1,500 @@
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray, BooleanArray, StringArray},
+    datatypes::{DataType, Field, Schema},
+    error::{ArrowError, Result},
+    record_batch::RecordBatch,
+};
+use arrow_flight_sql_client::{
+    arrow_flight_protocol_sql::*,
+    client::{FlightSqlServiceClient, IpcMessage},
+    sql_info::{parse_sql_info, sql_info_name_value_batch},
+};
+use rustyline::{error::ReadlineError, Editor};
+use std::{path::PathBuf, sync::Arc, time::Instant};
+
+use crate::{
+    completion::{Refreshed, ShellHelper},
+    format::OutputFormat,
+    get_and_print, print_batch,
+    stats::print_stats,
+    Common,
+};
+
+const HELP: &str = r"Statements end with a ';' and can span several lines.
+
+  \dt [pattern]   list the tables, optionally only those matching the pattern
+  \dn [pattern]   list the schemas, optionally only those matching the pattern
+  \d table        describe the columns of a table
+  \keys table     list the primary keys and imported foreign keys of a table
+  \info [name..]  show the SqlInfo of the server, or only the given names
+  \refresh        reload the names that are completed with tab
+  \?              show this help
+  \q              quit
+
+Tables can be qualified with their schema and catalog, as in catalog.schema.table.";
+
+/// Run an interactive prompt that executes statements and meta-commands with
+/// the client, until the user quits.
+#[tracing::instrument(skip_all)]
+pub async fn run_shell(
+    client: FlightSqlServiceClient,
+    common: &Common,
+    history_file: Option<PathBuf>,
+) -> Result<()> {
+    let helper = ShellHelper::default();
+    let mut editor = Editor::<ShellHelper>::new().map_err(readline_error_to_arrow_error)?;
+    editor.set_helper(Some(helper.clone()));
+    let history_file = history_file.or_else(default_history_file);
+    if let Some(history_file) = &history_file {
+        // The history file does not exist yet the first time the shell is used.
+        let _ = editor.load_history(history_file);
+    }
+
+    let address = match &common.uri {
+        Some(uri) => uri.clone(),
+        None => format!("{}:{}", common.hostname, common.port),
+    };
+    println!("Connected to {}, enter \\? for help.", address);
+    print_refresh_failures(&helper.refresh(&client).await);
+    let mut statement = String::new();
+    loop {
+        let prompt = if statement.is_empty() {
+            "flightsql> "
+        } else {
+            "        -> "
+        };
+        let line = match tokio::task::block_in_place(|| editor.readline(prompt)) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                statement.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(readline_error_to_arrow_error(err)),
+        };
+
+        if statement.is_empty() {
+            let command = line.trim();
+            if command == "quit" || command == "exit" {
+                break;
+            }
+            if command.starts_with('\\') {
+                editor.add_history_entry(command);
+                match run_meta_command(&client, &helper, common, command).await {
+                    Ok(true) => break,
+                    Ok(false) => {}
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        print_stats(&client, common);
+                    }
+                }
+                continue;
+            }
+        }
+
+        if !statement.is_empty() {
+            statement.push('\n');
+        }
+        statement.push_str(&line);
+        if statement.trim().is_empty() {
+            statement.clear();
+        } else if statement.trim_end().ends_with(';') {
+            editor.add_history_entry(statement.trim());
+            let query = statement.trim().trim_end_matches(';').trim().to_string();
+            statement.clear();
+            if !query.is_empty() {
+                if let Err(err) = run_statement(&client, common, query).await {
+                    eprintln!("Error: {}", err);
+                    print_stats(&client, common);
+                }
+            }
+        }
+    }
+
+    if let Some(history_file) = &history_file {
+        if let Some(parent) = history_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        editor
+            .save_history(history_file)
+            .map_err(readline_error_to_arrow_error)?;
+    }
+    Ok(())
+}
+
+/// The history is kept in the home directory of the user by default.
+fn default_history_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".arrow_flight_sql_client_history"))
+}
+
+/// The options of the shell, with the time at which the statement started,
+/// so that the footer of table output reports the time of each statement.
+/// Results are always printed, never written to the output file.
+fn statement_options(common: &Common) -> Common {
+    let mut common = common.clone();
+    common.format.started = Instant::now();
+    common.output.output = None;
+    common
+}
+
+/// Print the elapsed time, unless the footer of table output already did.
+fn print_elapsed(common: &Common) {
+    if common.format.format != OutputFormat::Table {
+        eprintln!(
+            "Time: {:.3}s",
+            common.format.started.elapsed().as_secs_f64()
+        );
+    }
+}
+
+/// Print the sources of names that could not be loaded, in a single message.
+fn print_refresh_failures(refreshed: &Refreshed) {
+    if !refreshed.failures.is_empty() {
+        let failures = refreshed
+            .failures
+            .iter()
+            .map(|(source, err)| format!("{}: {}", source, err))
+            .collect::<Vec<_>>();
+        eprintln!(
+            "Could not load some names to complete, {}",
+            failures.join("; ")
+        );
+    }
+}
+
+/// Execute a statement, as an update when it starts with a keyword that
+/// modifies data or the schema, as a query otherwise.
+#[tracing::instrument(skip_all)]
+async fn run_statement(
+    client: &FlightSqlServiceClient,
+    common: &Common,
+    query: String,
+) -> Result<()> {
+    let common = statement_options(common);
+    // The stats printed for the statement leave out the calls made before.
+    client.take_stats();
+    if is_update(&query) {
+        let record_count = client.execute_update(query).await?;
+        println!("Updated {} records.", record_count);
+        print_stats(client, &common);
+    } else {
+        let fi = client.execute(query).await?;
+        get_and_print(client.clone(), fi, &common).await?;
+    }
+    print_elapsed(&common);
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug")]
+fn is_update(query: &str) -> bool {
+    let keyword = query
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    matches!(
+        keyword.as_str(),
+        "insert"
+            | "update"
+            | "delete"
+            | "merge"
+            | "create"
+            | "drop"
+            | "alter"
+            | "truncate"
+            | "grant"
+            | "revoke"
+    )
+}
+
+/// Run a meta-command, and return whether the shell should quit.
+#[tracing::instrument(skip(client, helper, common))]
+async fn run_meta_command(
+    client: &FlightSqlServiceClient,
+    helper: &ShellHelper,
+    common: &Common,
+    command: &str,
+) -> Result<bool> {
+    let common = statement_options(common);
+    client.take_stats();
+    let mut args = command.split_whitespace();
+    let name = args.next().unwrap_or_default();
+    let args = args.collect::<Vec<_>>();
+    match name {
+        "\\q" => return Ok(true),
+        "\\?" | "\\h" | "\\help" => println!("{}", HELP),
+        "\\dt" => {
+            let fi = client
+                .get_tables(CommandGetTables {
+                    catalog: common.catalog.clone(),
+                    db_schema_filter_pattern: common.db_schema.clone(),
+                    table_name_filter_pattern: args.first().map(|x| x.to_string()),
+                    table_types: vec![],
+                    include_schema: false,
+                })
+                .await?;
+            get_and_print(client.clone(), fi, &common).await?;
+        }
+        "\\dn" => {
+            let fi = client
+                .get_db_schemas(CommandGetDbSchemas {
+                    catalog: common.catalog.clone(),
+                    db_schema_filter_pattern: args.first().map(|x| x.to_string()),
+                })
+                .await?;
+            get_and_print(client.clone(), fi, &common).await?;
+        }
+        "\\d" => {
+            let table = TableReference::parse(single_argument(name, &args)?, &common)?;
+            print_batch(describe_table(client, &table).await?, &common)?;
+            print_stats(client, &common);
+        }
+        "\\keys" => {
+            let table = TableReference::parse(single_argument(name, &args)?, &common)?;
+            println!("Primary keys");
+            let fi = client
+                .get_primary_keys(CommandGetPrimaryKeys {
+                    catalog: table.catalog.clone(),
+                    db_schema: table.db_schema.clone(),
+                    table: table.table.clone(),
+                })
+                .await?;
+            get_and_print(client.clone(), fi, &statement_options(&common)).await?;
+            println!("Imported keys");
+            let fi = client
+                .get_imported_keys(CommandGetImportedKeys {
+                    catalog: table.catalog,
+                    db_schema: table.db_schema,
+                    table: table.table,
+                })
+                .await?;
+            get_and_print(client.clone(), fi, &statement_options(&common)).await?;
+        }
+        "\\refresh" => {
+            let refreshed = helper.refresh(client).await;
+            println!("Loaded {} names to complete.", refreshed.names);
+            print_refresh_failures(&refreshed);
+        }
+        "\\info" => {
+            let sql_infos = args
+                .iter()
+                .map(|name| parse_sql_info(name))
+                .collect::<Result<Vec<_>>>()?;
+            let infos = client.get_sql_info_values(sql_infos).await?;
+            print_batch(sql_info_name_value_batch(&infos)?, &common)?;
+            print_stats(client, &common);
+        }
+        _ => {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "Unknown command {}, enter \\? for help",
+                name
+            )))
+        }
+    }
+    print_elapsed(&common);
+    Ok(false)
+}
+
+fn single_argument<'a>(command: &str, args: &[&'a str]) -> Result<&'a str> {
+    match args {
+        [arg] => Ok(arg),
+        _ => Err(ArrowError::InvalidArgumentError(format!(
+            "{} expects a single table",
+            command
+        ))),
+    }
+}
+
+/// A table, optionally qualified with its schema and catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TableReference {
+    catalog: Option<String>,
+    db_schema: Option<String>,
+    table: String,
+}
+
+impl TableReference {
+    /// Parse the reference, with the catalog and schema of the profile when
+    /// it does not name them.
+    fn parse(reference: &str, common: &Common) -> Result<Self> {
+        let parts = reference.split('.').map(str::to_string).collect::<Vec<_>>();
+        match parts.as_slice() {
+            [table] => Ok(TableReference {
+                catalog: common.catalog.clone(),
+                db_schema: common.db_schema.clone(),
+                table: table.clone(),
+            }),
+            [db_schema, table] => Ok(TableReference {
+                catalog: common.catalog.clone(),
+                db_schema: Some(db_schema.clone()),
+                table: table.clone(),
+            }),
+            [catalog, db_schema, table] => Ok(TableReference {
+                catalog: Some(catalog.clone()),
+                db_schema: Some(db_schema.clone()),
+                table: table.clone(),
+            }),
+            _ => Err(ArrowError::InvalidArgumentError(format!(
+                "Invalid table {}",
+                reference
+            ))),
+        }
+    }
+}
+
+/// Escape the `%` and `_` wildcards of a filter pattern, and the `\` escape
+/// character itself, so that the pattern only matches the name.
+fn escape_pattern(name: &str) -> String {
+    let mut pattern = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern
+}
+
+/// The serialized schema of the first row of the `get_tables` batches for
+/// exactly the table, as servers may ignore the escapes of the pattern.
+fn find_table_schema(batches: &[RecordBatch], table: &TableReference) -> Option<Vec<u8>> {
+    batches.iter().find_map(|batch| {
+        let column = |name: &str| -> Option<&StringArray> {
+            batch
+                .column(batch.schema().index_of(name).ok()?)
+                .as_any()
+                .downcast_ref()
+        };
+        let catalogs = column("catalog_name")?;
+        let db_schemas = column("db_schema_name")?;
+        let tables = column("table_name")?;
+        let table_schemas = batch
+            .column(batch.schema().index_of("table_schema").ok()?)
+            .as_any()
+            .downcast_ref::<BinaryArray>()?;
+        let matches = |names: &StringArray, expected: &Option<String>, row: usize| {
+            expected
+                .as_ref()
+                .is_none_or(|expected| names.is_valid(row) && names.value(row) == expected)
+        };
+        (0..batch.num_rows())
+            .find(|&row| {
+                tables.is_valid(row)
+                    && tables.value(row) == table.table
+                    && matches(db_schemas, &table.db_schema, row)
+                    && matches(catalogs, &table.catalog, row)
+            })
+            .map(|row| table_schemas.value(row).to_vec())
+    })
+}
+
+/// Request the schema of the table with `get_tables` and return its columns
+/// as a batch with their name, type and nullability.
+#[tracing::instrument(skip_all)]
+async fn describe_table(
+    client: &FlightSqlServiceClient,
+    table: &TableReference,
+) -> Result<RecordBatch> {
+    let fi = client
+        .get_tables(CommandGetTables {
+            catalog: table.catalog.clone(),
+            db_schema_filter_pattern: table.db_schema.as_deref().map(escape_pattern),
+            table_name_filter_pattern: Some(escape_pattern(&table.table)),
+            table_types: vec![],
+            include_schema: true,
+        })
+        .await?;
+    let batches = client.do_get_batches(&fi).await?;
+    let table_schema = find_table_schema(&batches, table).ok_or_else(|| {
+        ArrowError::InvalidArgumentError(format!("Table {} not found", table.table))
+    })?;
+    let schema = Schema::try_from(IpcMessage(table_schema))?;
+
+    let names: StringArray = schema.fields().iter().map(|f| Some(f.name())).collect();
+    let data_types: StringArray = schema
+        .fields()
+        .iter()
+        .map(|f| Some(f.data_type().to_string()))
+        .collect();
+    let nullables: BooleanArray = schema
+        .fields()
+        .iter()
+        .map(|f| Some(f.is_nullable()))
+        .collect();
+    RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            Field::new("column_name", DataType::Utf8, false),
+            Field::new("data_type", DataType::Utf8, false),
+            Field::new("nullable", DataType::Boolean, false),
+        ])),
+        vec![
+            Arc::new(names) as ArrayRef,
+            Arc::new(data_types),
+            Arc::new(nullables),
+        ],
+    )
+}
+
+fn readline_error_to_arrow_error(err: ReadlineError) -> ArrowError {
+    ArrowError::IoError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A batch of `get_tables` with the tables, whose schemas are their
+    /// qualified names.
+    fn tables_batch(rows: &[[&str; 3]]) -> RecordBatch {
+        let column =
+            |index: usize| -> StringArray { rows.iter().map(|row| Some(row[index])).collect() };
+        let schemas = rows.iter().map(|row| row.join(".")).collect::<Vec<_>>();
+        let schemas = BinaryArray::from(schemas.iter().map(String::as_bytes).collect::<Vec<_>>());
+        RecordBatch::try_from_iter(vec![
+            ("catalog_name", Arc::new(column(0)) as ArrayRef),
+            ("db_schema_name", Arc::new(column(1))),
+            ("table_name", Arc::new(column(2))),
+            ("table_schema", Arc::new(schemas)),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn escapes_wildcards_of_the_pattern() {
+        assert_eq!(escape_pattern("orders"), "orders");
+        assert_eq!(escape_pattern("my_table"), "my\\_table");
+        assert_eq!(escape_pattern("100%"), "100\\%");
+        assert_eq!(escape_pattern("a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn finds_only_the_exact_table() {
+        let batches = [tables_batch(&[
+            ["main", "app", "myXtable"],
+            ["main", "other", "my_table"],
+            ["main", "app", "my_table"],
+        ])];
+        let find = |catalog: Option<&str>, db_schema: Option<&str>, table: &str| {
+            let table = TableReference {
+                catalog: catalog.map(str::to_string),
+                db_schema: db_schema.map(str::to_string),
+                table: table.to_string(),
+            };
+            find_table_schema(&batches, &table).map(|schema| String::from_utf8(schema).unwrap())
+        };
+        assert_eq!(
+            find(None, None, "my_table").as_deref(),
+            Some("main.other.my_table")
+        );
+        assert_eq!(
+            find(None, Some("app"), "my_table").as_deref(),
+            Some("main.app.my_table")
+        );
+        assert_eq!(
+            find(Some("main"), Some("app"), "myXtable").as_deref(),
+            Some("main.app.myXtable")
+        );
+        assert_eq!(find(Some("other"), None, "my_table"), None);
+        assert_eq!(find(None, None, "my%"), None);
+    }
+}
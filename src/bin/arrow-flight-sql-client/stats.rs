@@ -0,0 +1,72 @@
+use arrow_flight_sql_client::{client::FlightSqlServiceClient, metrics::ClientStats};
+use std::time::Duration;
+
+use crate::Common;
+
+/// Print the stats of the calls made by the client on stderr when `--stats`
+/// is set, and start over so that the next summary covers the next query.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn print_stats(client: &FlightSqlServiceClient, common: &Common) {
+    let stats = client.take_stats();
+    if common.stats {
+        eprint!("{}", format_stats(&stats));
+    }
+}
+
+/// One line per kind of call that was made, and one with the errors.
+#[tracing::instrument(level = "debug", skip_all)]
+fn format_stats(stats: &ClientStats) -> String {
+    let mut lines = vec![];
+    if stats.get_flight_info_calls > 0 {
+        lines.push(format!(
+            "GetFlightInfo: {} in {}",
+            count(stats.get_flight_info_calls, "call"),
+            milliseconds(stats.get_flight_info_time)
+        ));
+    }
+    if stats.do_get_calls > 0 {
+        let first_batch = match stats.time_to_first_batch {
+            Some(elapsed) => format!("first batch after {}", milliseconds(elapsed)),
+            None => "no batches".to_string(),
+        };
+        lines.push(format!(
+            "DoGet: {}, {}, read in {}",
+            count(stats.do_get_calls, "stream"),
+            first_batch,
+            milliseconds(stats.do_get_time)
+        ));
+        lines.push(format!(
+            "Decoded: {}, {}, {}",
+            count(stats.rows, "row"),
+            count(stats.batches, "batch"),
+            count(stats.bytes, "byte")
+        ));
+    }
+    let mut errors = stats.errors.iter().collect::<Vec<_>>();
+    errors.sort_by_key(|(code, _)| **code as i32);
+    let errors = if errors.is_empty() {
+        "none".to_string()
+    } else {
+        errors
+            .iter()
+            .map(|(code, count)| format!("{} {:?}", count, code))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    lines.push(format!("Errors: {}", errors));
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn count(count: u64, noun: &str) -> String {
+    match (count, noun) {
+        (1, _) => format!("1 {}", noun),
+        (_, "batch") => format!("{} batches", count),
+        _ => format!("{} {}s", count, noun),
+    }
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,276 @@
+use arrow::{datatypes::SchemaRef, error::Result, record_batch::RecordBatch};
+use std::{io::Write, time::Instant};
+
+use crate::format::{row_to_strings, BatchWriter};
+
+/// The number of rows that are buffered to determine the width of the columns.
+const SAMPLE_ROWS: usize = 1000;
+
+/// Prints all batches as one continuous table, followed by a footer with the
+/// number of rows and the elapsed time.
+///
+/// The width of the columns is determined by the header and the first rows,
+/// the rows that follow are printed as they arrive and cells that do not fit
+/// are truncated.
+pub struct TableWriter<W: Write> {
+    output: W,
+    schema: SchemaRef,
+    max_width: usize,
+    started: Instant,
+    sample: Vec<Vec<String>>,
+    widths: Option<Vec<usize>>,
+    rows: usize,
+}
+
+impl<W: Write> TableWriter<W> {
+    /// Create a TableWriter that truncates cells to `max_width` characters,
+    /// unless it is 0, and reports the time elapsed since `started`.
+    pub fn new(output: W, schema: SchemaRef, max_width: usize, started: Instant) -> Self {
+        TableWriter {
+            output,
+            schema,
+            max_width,
+            started,
+            sample: vec![],
+            widths: None,
+            rows: 0,
+        }
+    }
+
+    /// Determine the width of the columns from the sample and print the
+    /// header and the sampled rows.
+    fn write_sample(&mut self) -> Result<()> {
+        let header = self
+            .schema
+            .fields()
+            .iter()
+            .map(|field| escape(field.name()))
+            .collect::<Vec<_>>();
+        let mut widths = header.iter().map(|name| width(name)).collect::<Vec<_>>();
+        for row in &self.sample {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(self::width(cell));
+            }
+        }
+        if self.max_width > 0 {
+            widths = widths.into_iter().map(|w| w.min(self.max_width)).collect();
+        }
+
+        write_border(&mut self.output, &widths)?;
+        write_row(&mut self.output, &widths, &header)?;
+        write_border(&mut self.output, &widths)?;
+        for row in std::mem::take(&mut self.sample) {
+            write_row(&mut self.output, &widths, &row)?;
+        }
+        self.widths = Some(widths);
+        Ok(())
+    }
+}
+
+impl<W: Write> BatchWriter for TableWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        for row in 0..batch.num_rows() {
+            let cells = row_to_strings(batch, row)?
+                .iter()
+                .map(|cell| escape(cell))
+                .collect::<Vec<_>>();
+            match &self.widths {
+                Some(widths) => write_row(&mut self.output, widths, &cells)?,
+                None => {
+                    self.sample.push(cells);
+                    if self.sample.len() >= SAMPLE_ROWS {
+                        self.write_sample()?;
+                    }
+                }
+            }
+        }
+        self.rows += batch.num_rows();
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.widths.is_none() {
+            self.write_sample()?;
+        }
+        if let Some(widths) = &self.widths {
+            write_border(&mut self.output, widths)?;
+        }
+        writeln!(
+            self.output,
+            "{} {} in {:.3}s",
+            self.rows,
+            if self.rows == 1 { "row" } else { "rows" },
+            self.started.elapsed().as_secs_f64()
+        )?;
+        Ok(self.output.flush()?)
+    }
+}
+
+fn write_border<W: Write>(output: &mut W, widths: &[usize]) -> Result<()> {
+    let border = widths
+        .iter()
+        .map(|width| "-".repeat(width + 2))
+        .collect::<Vec<_>>();
+    writeln!(output, "+{}+", border.join("+"))?;
+    Ok(())
+}
+
+fn write_row<W: Write>(output: &mut W, widths: &[usize], row: &[String]) -> Result<()> {
+    let cells = widths
+        .iter()
+        .zip(row)
+        .map(|(width, cell)| format!(" {} ", fit(cell, *width)))
+        .collect::<Vec<_>>();
+    writeln!(output, "|{}|", cells.join("|"))?;
+    Ok(())
+}
+
+/// Keep every cell on a single line.
+fn escape(cell: &str) -> String {
+    cell.replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+fn width(cell: &str) -> usize {
+    cell.chars().count()
+}
+
+/// Pad the cell to the width, or truncate it with an ellipsis when it is wider.
+fn fit(cell: &str, width: usize) -> String {
+    let cell_width = self::width(cell);
+    if cell_width > width {
+        let truncated = cell
+            .chars()
+            .take(width.saturating_sub(1))
+            .collect::<String>();
+        format!("{}…", truncated)
+    } else {
+        format!("{}{}", cell, " ".repeat(width - cell_width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{ArrayRef, DictionaryArray, Int32Array, StringArray},
+        datatypes::Int32Type,
+    };
+    use std::sync::Arc;
+
+    fn batch(names: Vec<Option<&str>>) -> RecordBatch {
+        let ids = (0..names.len() as i32)
+            .map(|id| (id > 0).then_some(id))
+            .collect::<Int32Array>();
+        let kinds = names
+            .iter()
+            .map(|name| name.map(|_| "kind"))
+            .collect::<DictionaryArray<Int32Type>>();
+        RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(ids) as ArrayRef),
+            ("name", Arc::new(StringArray::from(names)) as ArrayRef),
+            ("kind", Arc::new(kinds) as ArrayRef),
+        ])
+        .unwrap()
+    }
+
+    /// Write the batches and return the lines of the output, and the footer
+    /// without the elapsed time.
+    fn write(max_width: usize, batches: &[RecordBatch]) -> (Vec<String>, String) {
+        let schema = batch(vec![]).schema();
+        let mut writer = TableWriter::new(vec![], schema, max_width, Instant::now());
+        for batch in batches {
+            writer.write(batch).unwrap();
+        }
+        writer.finish().unwrap();
+        let output = String::from_utf8(writer.output).unwrap();
+        let mut lines = output.lines().map(str::to_string).collect::<Vec<_>>();
+        let footer = lines.pop().unwrap();
+        let (footer, _) = footer.split_once(" in ").unwrap();
+        (lines, footer.to_string())
+    }
+
+    #[test]
+    fn writes_one_table_for_all_batches() {
+        let (lines, footer) = write(
+            50,
+            &[
+                batch(vec![Some("a"), None]),
+                batch(vec![Some("line\nbreak")]),
+            ],
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "+----+-------------+------+",
+                "| id | name        | kind |",
+                "+----+-------------+------+",
+                "|    | a           | kind |",
+                "| 1  |             |      |",
+                "|    | line\\nbreak | kind |",
+                "+----+-------------+------+",
+            ]
+        );
+        assert_eq!(footer, "3 rows");
+    }
+
+    #[test]
+    fn truncates_cells_wider_than_max_width() {
+        let (lines, _) = write(4, &[batch(vec![Some("abcdef"), Some("abcd")])]);
+        assert_eq!(
+            lines,
+            vec![
+                "+----+------+------+",
+                "| id | name | kind |",
+                "+----+------+------+",
+                "|    | abc… | kind |",
+                "| 1  | abcd | kind |",
+                "+----+------+------+",
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_truncate_without_max_width() {
+        let name = "x".repeat(100);
+        let (lines, _) = write(0, &[batch(vec![Some(&name)])]);
+        assert_eq!(lines[3], format!("|    | {} | kind |", name));
+    }
+
+    #[test]
+    fn truncates_rows_after_the_sample_to_its_widths() {
+        let (lines, footer) = write(
+            50,
+            &[
+                batch(vec![Some("a"); SAMPLE_ROWS]),
+                batch(vec![Some("abcdef")]),
+            ],
+        );
+        assert_eq!(lines[1], "| id  | name | kind |");
+        assert_eq!(lines[lines.len() - 2], "|     | abc… | kind |");
+        assert_eq!(footer, "1001 rows");
+    }
+
+    #[test]
+    fn writes_header_of_empty_result() {
+        let (lines, footer) = write(50, &[]);
+        assert_eq!(
+            lines,
+            vec![
+                "+----+------+------+",
+                "| id | name | kind |",
+                "+----+------+------+",
+                "+----+------+------+",
+            ]
+        );
+        assert_eq!(footer, "0 rows");
+    }
+
+    #[test]
+    fn counts_a_single_row() {
+        let (_, footer) = write(50, &[batch(vec![Some("a")])]);
+        assert_eq!(footer, "1 row");
+    }
+}
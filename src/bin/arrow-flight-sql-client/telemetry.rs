@@ -0,0 +1,136 @@
+use arrow::error::{ArrowError, Result};
+use arrow_flight_sql_client::tracing::{
+    tracer_provider, TelemetryOptions, TraceExporter, DEFAULT_OTLP_GRPC_ENDPOINT,
+    DEFAULT_OTLP_HTTP_ENDPOINT,
+};
+use clap::{ArgEnum, Args};
+use opentelemetry::{
+    baggage::BaggageExt,
+    global,
+    propagation::TextMapPropagator,
+    sdk::propagation::{BaggagePropagator, TextMapCompositePropagator, TraceContextPropagator},
+    trace::TracerProvider,
+    Context, ContextGuard, KeyValue,
+};
+use std::path::PathBuf;
+
+use crate::parse_header;
+use tracing_subscriber::{
+    filter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer, Registry,
+};
+
+/// The exporters of the spans of the client.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceExporterKind {
+    None,
+    Stderr,
+    Pretty,
+    JsonFile,
+    OtlpGrpc,
+    OtlpHttp,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TelemetryArgs {
+    /// Where the spans of the client are exported to, stderr and pretty print them on one or several lines of stderr
+    #[clap(
+        long,
+        env = "FLIGHT_SQL_TRACE_EXPORTER",
+        arg_enum,
+        default_value = "none"
+    )]
+    pub trace_exporter: TraceExporterKind,
+    /// Endpoint of the OpenTelemetry collector, http://localhost:4317 for otlp-grpc and http://localhost:4318/v1/traces for otlp-http by default
+    #[clap(long, env = "FLIGHT_SQL_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+    /// File the json-file exporter writes the spans to, one json object per line
+    #[clap(long, env = "FLIGHT_SQL_TRACE_FILE", parse(from_os_str))]
+    pub trace_file: Option<PathBuf>,
+    /// Fraction of the traces that are sampled, between 0 and 1
+    #[clap(long, env = "FLIGHT_SQL_TRACE_SAMPLE_RATIO", default_value_t = 1.0)]
+    pub trace_sample_ratio: f64,
+    /// Baggage sent to the server with the trace context, as key=value
+    #[clap(long, env = "FLIGHT_SQL_BAGGAGE", parse(try_from_str = parse_header), multiple_occurrences = true)]
+    pub baggage: Vec<(String, String)>,
+}
+
+impl TelemetryArgs {
+    fn options(&self) -> Result<TelemetryOptions> {
+        let endpoint = |default: &str| {
+            self.otlp_endpoint
+                .clone()
+                .unwrap_or_else(|| default.to_string())
+        };
+        let exporter = match self.trace_exporter {
+            TraceExporterKind::None => TraceExporter::None,
+            TraceExporterKind::Stderr => TraceExporter::Stderr,
+            TraceExporterKind::Pretty => TraceExporter::Pretty,
+            TraceExporterKind::JsonFile => match &self.trace_file {
+                Some(path) => TraceExporter::JsonFile(path.clone()),
+                None => {
+                    return Err(ArrowError::InvalidArgumentError(
+                        "--trace-exporter json-file requires --trace-file".to_string(),
+                    ))
+                }
+            },
+            TraceExporterKind::OtlpGrpc => {
+                TraceExporter::OtlpGrpc(endpoint(DEFAULT_OTLP_GRPC_ENDPOINT))
+            }
+            TraceExporterKind::OtlpHttp => {
+                TraceExporter::OtlpHttp(endpoint(DEFAULT_OTLP_HTTP_ENDPOINT))
+            }
+        };
+        Ok(TelemetryOptions {
+            exporter,
+            sample_ratio: self.trace_sample_ratio,
+        })
+    }
+}
+
+/// Install the global subscriber, which logs on stderr and exports the spans
+/// as configured, and the global tracer provider, which is shut down when
+/// the client exits to export the remaining spans.
+///
+/// The trace context, and the baggage when there is any, are sent with every
+/// request. The baggage is attached to the returned guard, and applies to the
+/// spans started while it is held.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn setup_telemetry(args: &TelemetryArgs) -> Result<ContextGuard> {
+    let provider = tracer_provider(&args.options()?)?;
+
+    let env_filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new("warn,arrow_flight_sql_client=debug"))
+        .map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))?;
+
+    let log_layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+
+    let telemetry_layer = provider.map(|provider| {
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        global::set_tracer_provider(provider);
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_exception_field_propagation(true)
+            .with_tracked_inactivity(true)
+            .with_filter(filter::LevelFilter::INFO)
+    });
+
+    Registry::default()
+        .with(env_filter)
+        .with(log_layer)
+        .with(telemetry_layer)
+        .try_init()
+        .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+
+    let mut propagators: Vec<Box<dyn TextMapPropagator + Send + Sync>> =
+        vec![Box::new(TraceContextPropagator::new())];
+    if !args.baggage.is_empty() {
+        propagators.push(Box::new(BaggagePropagator::new()));
+    }
+    global::set_text_map_propagator(TextMapCompositePropagator::new(propagators));
+
+    let baggage = args
+        .baggage
+        .iter()
+        .map(|(key, value)| KeyValue::new(key.clone(), value.clone()));
+    Ok(Context::current_with_baggage(baggage).attach())
+}
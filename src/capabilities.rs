@@ -0,0 +1,498 @@
+use std::collections::BTreeMap;
+
+use crate::arrow_flight_protocol_sql::*;
+use crate::sql_info::{sql_info_name, SqlInfoValue};
+
+/// The capabilities of a Flight SQL server, decoded from the values of all the
+/// SqlInfos it returns.
+///
+/// Information the server did not return, or returned with another type than
+/// the one defined in FlightSql.proto, is `None`, or empty for lists and
+/// bitmasks. Bitmasks are decoded into the list of enum values whose bit is
+/// set, ordinals into their enum value. Bits and ordinals that are not defined
+/// in FlightSql.proto are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerCapabilities {
+    pub server_name: Option<String>,
+    pub server_version: Option<String>,
+    pub server_arrow_version: Option<String>,
+    pub read_only: Option<bool>,
+
+    pub ddl_catalog: Option<bool>,
+    pub ddl_schema: Option<bool>,
+    pub ddl_table: Option<bool>,
+    pub identifier_case: Option<SqlSupportedCaseSensitivity>,
+    pub identifier_quote_char: Option<String>,
+    pub quoted_identifier_case: Option<SqlSupportedCaseSensitivity>,
+    pub all_tables_are_selectable: Option<bool>,
+    pub null_ordering: Option<SqlNullOrdering>,
+    pub keywords: Vec<String>,
+    pub numeric_functions: Vec<String>,
+    pub string_functions: Vec<String>,
+    pub system_functions: Vec<String>,
+    pub datetime_functions: Vec<String>,
+    pub search_string_escape: Option<String>,
+    pub extra_name_characters: Option<String>,
+    pub supports_column_aliasing: Option<bool>,
+    pub null_plus_null_is_null: Option<bool>,
+    /// The types each type can be converted to.
+    pub supports_convert: BTreeMap<SqlSupportsConvert, Vec<SqlSupportsConvert>>,
+    pub supports_table_correlation_names: Option<bool>,
+    pub supports_different_table_correlation_names: Option<bool>,
+    pub supports_expressions_in_order_by: Option<bool>,
+    pub supports_order_by_unrelated: Option<bool>,
+    pub supported_group_by: Vec<SqlSupportedGroupBy>,
+    pub supports_like_escape_clause: Option<bool>,
+    pub supports_non_nullable_columns: Option<bool>,
+    pub supported_grammar: Vec<SupportedSqlGrammar>,
+    pub ansi92_supported_level: Vec<SupportedAnsi92SqlGrammarLevel>,
+    pub supports_integrity_enhancement_facility: Option<bool>,
+    pub outer_joins_support_level: Option<SqlOuterJoinsSupportLevel>,
+    pub schema_term: Option<String>,
+    pub procedure_term: Option<String>,
+    pub catalog_term: Option<String>,
+    pub catalog_at_start: Option<bool>,
+    pub schemas_supported_actions: Vec<SqlSupportedElementActions>,
+    pub catalogs_supported_actions: Vec<SqlSupportedElementActions>,
+    pub supported_positioned_commands: Vec<SqlSupportedPositionedCommands>,
+    pub select_for_update_supported: Option<bool>,
+    pub stored_procedures_supported: Option<bool>,
+    pub supported_subqueries: Vec<SqlSupportedSubqueries>,
+    pub correlated_subqueries_supported: Option<bool>,
+    pub supported_unions: Vec<SqlSupportedUnions>,
+
+    pub max_binary_literal_length: Option<i64>,
+    pub max_char_literal_length: Option<i64>,
+    pub max_column_name_length: Option<i64>,
+    pub max_columns_in_group_by: Option<i64>,
+    pub max_columns_in_index: Option<i64>,
+    pub max_columns_in_order_by: Option<i64>,
+    pub max_columns_in_select: Option<i64>,
+    pub max_columns_in_table: Option<i64>,
+    pub max_connections: Option<i64>,
+    pub max_cursor_name_length: Option<i64>,
+    pub max_index_length: Option<i64>,
+    pub db_schema_name_length: Option<i64>,
+    pub max_procedure_name_length: Option<i64>,
+    pub max_catalog_name_length: Option<i64>,
+    pub max_row_size: Option<i64>,
+    pub max_row_size_includes_blobs: Option<bool>,
+    pub max_statement_length: Option<i64>,
+    pub max_statements: Option<i64>,
+    pub max_table_name_length: Option<i64>,
+    pub max_tables_in_select: Option<i64>,
+    pub max_username_length: Option<i64>,
+
+    pub default_transaction_isolation: Option<SqlTransactionIsolationLevel>,
+    pub transactions_supported: Option<bool>,
+    pub supported_transactions_isolation_levels: Vec<SqlTransactionIsolationLevel>,
+    pub data_definition_causes_transaction_commit: Option<bool>,
+    pub data_definitions_in_transactions_ignored: Option<bool>,
+    pub supported_result_set_types: Vec<SqlSupportedResultSetType>,
+    pub supported_concurrencies_for_result_set_unspecified: Vec<SqlSupportedResultSetConcurrency>,
+    pub supported_concurrencies_for_result_set_forward_only: Vec<SqlSupportedResultSetConcurrency>,
+    pub supported_concurrencies_for_result_set_scroll_sensitive:
+        Vec<SqlSupportedResultSetConcurrency>,
+    pub supported_concurrencies_for_result_set_scroll_insensitive:
+        Vec<SqlSupportedResultSetConcurrency>,
+    pub batch_updates_supported: Option<bool>,
+    pub savepoints_supported: Option<bool>,
+    pub named_parameters_supported: Option<bool>,
+    pub locators_update_copy: Option<bool>,
+    pub stored_functions_using_call_syntax_supported: Option<bool>,
+
+    /// The values of SqlInfos that are not defined in FlightSql.proto, such as
+    /// vendor specific information.
+    pub other: BTreeMap<u32, SqlInfoValue>,
+    /// The values of SqlInfos that do not have the type defined in
+    /// FlightSql.proto, whose fields are left unset.
+    pub invalid: BTreeMap<u32, SqlInfoValue>,
+}
+
+impl ServerCapabilities {
+    /// Decode the values of SqlInfos, keyed by SqlInfo number, as returned by
+    /// `decode_sql_info_batches`.
+    ///
+    /// A value that does not have the type defined in FlightSql.proto leaves
+    /// its field unset, and is kept in `invalid`.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_sql_info(infos: BTreeMap<u32, SqlInfoValue>) -> Self {
+        let mut infos = SqlInfoValues {
+            values: infos,
+            invalid: BTreeMap::new(),
+        };
+        ServerCapabilities {
+            server_name: infos.string(SqlInfo::FlightSqlServerName),
+            server_version: infos.string(SqlInfo::FlightSqlServerVersion),
+            server_arrow_version: infos.string(SqlInfo::FlightSqlServerArrowVersion),
+            read_only: infos.bool(SqlInfo::FlightSqlServerReadOnly),
+
+            ddl_catalog: infos.bool(SqlInfo::SqlDdlCatalog),
+            ddl_schema: infos.bool(SqlInfo::SqlDdlSchema),
+            ddl_table: infos.bool(SqlInfo::SqlDdlTable),
+            identifier_case: infos.ordinal(
+                SqlInfo::SqlIdentifierCase,
+                SqlSupportedCaseSensitivity::from_i32,
+            ),
+            identifier_quote_char: infos.string(SqlInfo::SqlIdentifierQuoteChar),
+            quoted_identifier_case: infos.ordinal(
+                SqlInfo::SqlQuotedIdentifierCase,
+                SqlSupportedCaseSensitivity::from_i32,
+            ),
+            all_tables_are_selectable: infos.bool(SqlInfo::SqlAllTablesAreSelectable),
+            null_ordering: infos.ordinal(SqlInfo::SqlNullOrdering, SqlNullOrdering::from_i32),
+            keywords: infos.string_list(SqlInfo::SqlKeywords),
+            numeric_functions: infos.string_list(SqlInfo::SqlNumericFunctions),
+            string_functions: infos.string_list(SqlInfo::SqlStringFunctions),
+            system_functions: infos.string_list(SqlInfo::SqlSystemFunctions),
+            datetime_functions: infos.string_list(SqlInfo::SqlDatetimeFunctions),
+            search_string_escape: infos.string(SqlInfo::SqlSearchStringEscape),
+            extra_name_characters: infos.string(SqlInfo::SqlExtraNameCharacters),
+            supports_column_aliasing: infos.bool(SqlInfo::SqlSupportsColumnAliasing),
+            null_plus_null_is_null: infos.bool(SqlInfo::SqlNullPlusNullIsNull),
+            supports_convert: infos.supports_convert(),
+            supports_table_correlation_names: infos.bool(SqlInfo::SqlSupportsTableCorrelationNames),
+            supports_different_table_correlation_names: infos
+                .bool(SqlInfo::SqlSupportsDifferentTableCorrelationNames),
+            supports_expressions_in_order_by: infos.bool(SqlInfo::SqlSupportsExpressionsInOrderBy),
+            supports_order_by_unrelated: infos.bool(SqlInfo::SqlSupportsOrderByUnrelated),
+            supported_group_by: infos
+                .bitmask(SqlInfo::SqlSupportedGroupBy, SqlSupportedGroupBy::from_i32),
+            supports_like_escape_clause: infos.bool(SqlInfo::SqlSupportsLikeEscapeClause),
+            supports_non_nullable_columns: infos.bool(SqlInfo::SqlSupportsNonNullableColumns),
+            supported_grammar: infos
+                .bitmask(SqlInfo::SqlSupportedGrammar, SupportedSqlGrammar::from_i32),
+            ansi92_supported_level: infos.bitmask(
+                SqlInfo::SqlAnsi92SupportedLevel,
+                SupportedAnsi92SqlGrammarLevel::from_i32,
+            ),
+            supports_integrity_enhancement_facility: infos
+                .bool(SqlInfo::SqlSupportsIntegrityEnhancementFacility),
+            outer_joins_support_level: infos.ordinal(
+                SqlInfo::SqlOuterJoinsSupportLevel,
+                SqlOuterJoinsSupportLevel::from_i32,
+            ),
+            schema_term: infos.string(SqlInfo::SqlSchemaTerm),
+            procedure_term: infos.string(SqlInfo::SqlProcedureTerm),
+            catalog_term: infos.string(SqlInfo::SqlCatalogTerm),
+            catalog_at_start: infos.bool(SqlInfo::SqlCatalogAtStart),
+            schemas_supported_actions: infos.bitmask(
+                SqlInfo::SqlSchemasSupportedActions,
+                SqlSupportedElementActions::from_i32,
+            ),
+            catalogs_supported_actions: infos.bitmask(
+                SqlInfo::SqlCatalogsSupportedActions,
+                SqlSupportedElementActions::from_i32,
+            ),
+            supported_positioned_commands: infos.bitmask(
+                SqlInfo::SqlSupportedPositionedCommands,
+                SqlSupportedPositionedCommands::from_i32,
+            ),
+            select_for_update_supported: infos.bool(SqlInfo::SqlSelectForUpdateSupported),
+            stored_procedures_supported: infos.bool(SqlInfo::SqlStoredProceduresSupported),
+            supported_subqueries: infos.bitmask(
+                SqlInfo::SqlSupportedSubqueries,
+                SqlSupportedSubqueries::from_i32,
+            ),
+            correlated_subqueries_supported: infos.bool(SqlInfo::SqlCorrelatedSubqueriesSupported),
+            supported_unions: infos
+                .bitmask(SqlInfo::SqlSupportedUnions, SqlSupportedUnions::from_i32),
+
+            max_binary_literal_length: infos.bigint(SqlInfo::SqlMaxBinaryLiteralLength),
+            max_char_literal_length: infos.bigint(SqlInfo::SqlMaxCharLiteralLength),
+            max_column_name_length: infos.bigint(SqlInfo::SqlMaxColumnNameLength),
+            max_columns_in_group_by: infos.bigint(SqlInfo::SqlMaxColumnsInGroupBy),
+            max_columns_in_index: infos.bigint(SqlInfo::SqlMaxColumnsInIndex),
+            max_columns_in_order_by: infos.bigint(SqlInfo::SqlMaxColumnsInOrderBy),
+            max_columns_in_select: infos.bigint(SqlInfo::SqlMaxColumnsInSelect),
+            max_columns_in_table: infos.bigint(SqlInfo::SqlMaxColumnsInTable),
+            max_connections: infos.bigint(SqlInfo::SqlMaxConnections),
+            max_cursor_name_length: infos.bigint(SqlInfo::SqlMaxCursorNameLength),
+            max_index_length: infos.bigint(SqlInfo::SqlMaxIndexLength),
+            db_schema_name_length: infos.bigint(SqlInfo::SqlDbSchemaNameLength),
+            max_procedure_name_length: infos.bigint(SqlInfo::SqlMaxProcedureNameLength),
+            max_catalog_name_length: infos.bigint(SqlInfo::SqlMaxCatalogNameLength),
+            max_row_size: infos.bigint(SqlInfo::SqlMaxRowSize),
+            max_row_size_includes_blobs: infos.bool(SqlInfo::SqlMaxRowSizeIncludesBlobs),
+            max_statement_length: infos.bigint(SqlInfo::SqlMaxStatementLength),
+            max_statements: infos.bigint(SqlInfo::SqlMaxStatements),
+            max_table_name_length: infos.bigint(SqlInfo::SqlMaxTableNameLength),
+            max_tables_in_select: infos.bigint(SqlInfo::SqlMaxTablesInSelect),
+            max_username_length: infos.bigint(SqlInfo::SqlMaxUsernameLength),
+
+            default_transaction_isolation: infos.ordinal(
+                SqlInfo::SqlDefaultTransactionIsolation,
+                SqlTransactionIsolationLevel::from_i32,
+            ),
+            transactions_supported: infos.bool(SqlInfo::SqlTransactionsSupported),
+            supported_transactions_isolation_levels: infos.bitmask(
+                SqlInfo::SqlSupportedTransactionsIsolationLevels,
+                SqlTransactionIsolationLevel::from_i32,
+            ),
+            data_definition_causes_transaction_commit: infos
+                .bool(SqlInfo::SqlDataDefinitionCausesTransactionCommit),
+            data_definitions_in_transactions_ignored: infos
+                .bool(SqlInfo::SqlDataDefinitionsInTransactionsIgnored),
+            supported_result_set_types: infos.bitmask(
+                SqlInfo::SqlSupportedResultSetTypes,
+                SqlSupportedResultSetType::from_i32,
+            ),
+            supported_concurrencies_for_result_set_unspecified: infos.bitmask(
+                SqlInfo::SqlSupportedConcurrenciesForResultSetUnspecified,
+                SqlSupportedResultSetConcurrency::from_i32,
+            ),
+            supported_concurrencies_for_result_set_forward_only: infos.bitmask(
+                SqlInfo::SqlSupportedConcurrenciesForResultSetForwardOnly,
+                SqlSupportedResultSetConcurrency::from_i32,
+            ),
+            supported_concurrencies_for_result_set_scroll_sensitive: infos.bitmask(
+                SqlInfo::SqlSupportedConcurrenciesForResultSetScrollSensitive,
+                SqlSupportedResultSetConcurrency::from_i32,
+            ),
+            supported_concurrencies_for_result_set_scroll_insensitive: infos.bitmask(
+                SqlInfo::SqlSupportedConcurrenciesForResultSetScrollInsensitive,
+                SqlSupportedResultSetConcurrency::from_i32,
+            ),
+            batch_updates_supported: infos.bool(SqlInfo::SqlBatchUpdatesSupported),
+            savepoints_supported: infos.bool(SqlInfo::SqlSavepointsSupported),
+            named_parameters_supported: infos.bool(SqlInfo::SqlNamedParametersSupported),
+            locators_update_copy: infos.bool(SqlInfo::SqlLocatorsUpdateCopy),
+            stored_functions_using_call_syntax_supported: infos
+                .bool(SqlInfo::SqlStoredFunctionsUsingCallSyntaxSupported),
+
+            other: infos.values,
+            invalid: infos.invalid,
+        }
+    }
+}
+
+/// The SqlInfo values that are not decoded yet, and those that could not be.
+struct SqlInfoValues {
+    values: BTreeMap<u32, SqlInfoValue>,
+    invalid: BTreeMap<u32, SqlInfoValue>,
+}
+
+impl SqlInfoValues {
+    /// Take the value of the SqlInfo and decode it, or keep it as invalid
+    /// when it does not have the expected type.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn take<T>(
+        &mut self,
+        info: SqlInfo,
+        decode: fn(SqlInfoValue) -> Result<T, SqlInfoValue>,
+    ) -> Option<T> {
+        let value = self.values.remove(&(info as u32))?;
+        match decode(value) {
+            Ok(value) => Some(value),
+            Err(value) => {
+                tracing::warn!(
+                    "Ignoring the value of {} of an unexpected type: {:?}",
+                    sql_info_name(info as u32),
+                    value
+                );
+                self.invalid.insert(info as u32, value);
+                None
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn string(&mut self, info: SqlInfo) -> Option<String> {
+        self.take(info, |value| match value {
+            SqlInfoValue::String(value) => Ok(value),
+            value => Err(value),
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn bool(&mut self, info: SqlInfo) -> Option<bool> {
+        self.take(info, |value| match value {
+            SqlInfoValue::Bool(value) => Ok(value),
+            value => Err(value),
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn bigint(&mut self, info: SqlInfo) -> Option<i64> {
+        self.take(info, |value| match value {
+            SqlInfoValue::BigInt(value) => Ok(value),
+            value => Err(value),
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn string_list(&mut self, info: SqlInfo) -> Vec<String> {
+        self.take(info, |value| match value {
+            SqlInfoValue::StringList(values) => Ok(values),
+            value => Err(value),
+        })
+        .unwrap_or_default()
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn int32(&mut self, info: SqlInfo) -> Option<i32> {
+        self.take(info, |value| match value {
+            SqlInfoValue::Int32Bitmask(value) => Ok(value),
+            value => Err(value),
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn ordinal<T>(&mut self, info: SqlInfo, from_i32: fn(i32) -> Option<T>) -> Option<T> {
+        self.int32(info).and_then(from_i32)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn bitmask<T>(&mut self, info: SqlInfo, from_i32: fn(i32) -> Option<T>) -> Vec<T> {
+        self.int32(info)
+            .map(|bitmask| {
+                (0..i32::BITS as i32)
+                    .filter(|bit| bitmask & (1 << bit) != 0)
+                    .filter_map(from_i32)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn supports_convert(&mut self) -> BTreeMap<SqlSupportsConvert, Vec<SqlSupportsConvert>> {
+        self.take(SqlInfo::SqlSupportsConvert, |value| match value {
+            SqlInfoValue::Int32ToInt32ListMap(map) => Ok(map),
+            value => Err(value),
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(from, to)| {
+            Some((
+                SqlSupportsConvert::from_i32(from)?,
+                to.into_iter()
+                    .filter_map(SqlSupportsConvert::from_i32)
+                    .collect(),
+            ))
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(infos: Vec<(SqlInfo, SqlInfoValue)>) -> ServerCapabilities {
+        ServerCapabilities::from_sql_info(
+            infos
+                .into_iter()
+                .map(|(info, value)| (info as u32, value))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn decodes_typed_values() {
+        let capabilities = capabilities(vec![
+            (
+                SqlInfo::FlightSqlServerName,
+                SqlInfoValue::String("server".to_string()),
+            ),
+            (SqlInfo::FlightSqlServerReadOnly, SqlInfoValue::Bool(true)),
+            (SqlInfo::SqlMaxConnections, SqlInfoValue::BigInt(100)),
+            (
+                SqlInfo::SqlKeywords,
+                SqlInfoValue::StringList(vec!["SELECT".to_string(), "FROM".to_string()]),
+            ),
+        ]);
+        assert_eq!(capabilities.server_name.as_deref(), Some("server"));
+        assert_eq!(capabilities.read_only, Some(true));
+        assert_eq!(capabilities.max_connections, Some(100));
+        assert_eq!(capabilities.keywords, vec!["SELECT", "FROM"]);
+        assert_eq!(capabilities.server_version, None);
+        assert!(capabilities.other.is_empty());
+        assert!(capabilities.invalid.is_empty());
+    }
+
+    #[test]
+    fn decodes_bitmasks_and_ordinals() {
+        let capabilities = capabilities(vec![
+            // Both defined bits and an undefined one.
+            (
+                SqlInfo::SqlSupportedGroupBy,
+                SqlInfoValue::Int32Bitmask(0b1011),
+            ),
+            (
+                SqlInfo::SqlNullOrdering,
+                SqlInfoValue::Int32Bitmask(SqlNullOrdering::SqlNullsSortedAtEnd as i32),
+            ),
+            (SqlInfo::SqlIdentifierCase, SqlInfoValue::Int32Bitmask(42)),
+            (
+                SqlInfo::SqlSupportsConvert,
+                SqlInfoValue::Int32ToInt32ListMap(BTreeMap::from([
+                    (0, vec![7, 1000]),
+                    (1000, vec![0]),
+                ])),
+            ),
+        ]);
+        assert_eq!(
+            capabilities.supported_group_by,
+            vec![
+                SqlSupportedGroupBy::SqlGroupByUnrelated,
+                SqlSupportedGroupBy::SqlGroupByBeyondSelect
+            ]
+        );
+        assert_eq!(
+            capabilities.null_ordering,
+            Some(SqlNullOrdering::SqlNullsSortedAtEnd)
+        );
+        assert_eq!(capabilities.identifier_case, None);
+        assert_eq!(
+            capabilities.supports_convert,
+            BTreeMap::from([(
+                SqlSupportsConvert::SqlConvertBigint,
+                vec![SqlSupportsConvert::SqlConvertInteger]
+            )])
+        );
+        assert!(capabilities.supported_unions.is_empty());
+    }
+
+    #[test]
+    fn keeps_values_of_unknown_sql_infos() {
+        let capabilities = ServerCapabilities::from_sql_info(BTreeMap::from([(
+            10_000,
+            SqlInfoValue::String("vendor".to_string()),
+        )]));
+        assert_eq!(
+            capabilities.other,
+            BTreeMap::from([(10_000, SqlInfoValue::String("vendor".to_string()))])
+        );
+    }
+
+    #[test]
+    fn skips_values_of_another_type() {
+        let capabilities = capabilities(vec![
+            (SqlInfo::FlightSqlServerName, SqlInfoValue::Bool(true)),
+            (
+                SqlInfo::FlightSqlServerVersion,
+                SqlInfoValue::String("1.0".to_string()),
+            ),
+            (
+                SqlInfo::SqlSupportedGroupBy,
+                SqlInfoValue::String("all".to_string()),
+            ),
+        ]);
+        assert_eq!(capabilities.server_name, None);
+        assert_eq!(capabilities.server_version.as_deref(), Some("1.0"));
+        assert!(capabilities.supported_group_by.is_empty());
+        assert_eq!(
+            capabilities.invalid,
+            BTreeMap::from([
+                (
+                    SqlInfo::FlightSqlServerName as u32,
+                    SqlInfoValue::Bool(true)
+                ),
+                (
+                    SqlInfo::SqlSupportedGroupBy as u32,
+                    SqlInfoValue::String("all".to_string())
+                ),
+            ])
+        );
+    }
+}
@@ -1,17 +1,15 @@
-use std::cell::{RefCell, RefMut};
-
 use arrow::{
-    datatypes::{Schema, SchemaRef},
-    error::{ArrowError, Result},
+    array::ArrayRef,
+    datatypes::{DataType, Schema, SchemaRef},
+    error::{ArrowError, Result as ArrowResult},
     ipc::convert,
+    ipc::writer::{DictionaryTracker, IpcDataGenerator, IpcWriteOptions},
     ipc::*,
+    record_batch::RecordBatch,
 };
-use futures::stream;
+use futures::{future, stream, stream::BoxStream, StreamExt, TryStreamExt};
 use prost::Message;
-use tonic::{
-    codegen::{Body, StdError},
-    Streaming,
-};
+use tonic::{codegen::InterceptedService, transport::Channel, Streaming};
 
 use crate::arrow_flight_protocol::{flight_service_client::FlightServiceClient, *};
 
@@ -19,136 +17,371 @@ use crate::arrow_flight_protocol::flight_descriptor::DescriptorType;
 use crate::arrow_flight_protocol::FlightDescriptor;
 
 use std::collections::HashMap;
-use std::{convert::TryFrom, ops::Deref};
+use std::{
+    convert::TryFrom,
+    ops::Deref,
+    time::{Duration, Instant},
+};
 
 use crate::arrow_flight_protocol_sql::*;
+use crate::capabilities::ServerCapabilities;
+use crate::connection::ClientBuilder;
+use crate::error::{FlightSqlError, Result};
+use crate::interceptor::{bearer_token, HeaderInterceptor, WithoutDeadline, AUTHORIZATION_HEADER};
+use crate::metrics::{ClientMetrics, ClientStats};
+use crate::retry::{retry, RetryPolicy};
+use crate::sql_info::{decode_sql_info, SqlInfoValue};
+use crate::tracing::{record_response, record_status, rpc_span};
+use crate::validation::{read_dictionary_values, read_record_batch, validate_schema};
+use tracing::{Instrument, Span};
+
+/// A FlightServiceClient that adds the headers of a HeaderInterceptor to
+/// every request.
+pub type InterceptedClient = FlightServiceClient<InterceptedService<Channel, HeaderInterceptor>>;
+
+/// A stream of RecordBatches decoded from the data returned by DoGet.
+pub type RecordBatchStream = BoxStream<'static, Result<RecordBatch>>;
 
 /// A FlightSQLServiceClient is an endpoint for retrieving or storing Arrow data
 /// by FlightSQL protocol.
+///
+/// The client is cheap to clone: all clones share the same underlying
+/// `Channel`, so they can be used from different tasks at the same time.
+/// They also share the headers that are sent with every request, including
+/// the bearer token obtained by `handshake`, and the stats of the calls.
 #[derive(Debug, Clone)]
-pub struct FlightSqlServiceClient<T> {
-    inner: RefCell<FlightServiceClient<T>>,
-}
-
-impl<T> FlightSqlServiceClient<T>
-where
-    T: tonic::client::GrpcService<tonic::body::BoxBody>,
-    T::Error: Into<StdError>,
-    T::ResponseBody: Default + Body<Data = bytes::Bytes> + Send + 'static,
-    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
-{
-    /// create FlightSqlServiceClient using FlightServiceClient
+pub struct FlightSqlServiceClient {
+    inner: InterceptedClient,
+    headers: HeaderInterceptor,
+    metrics: ClientMetrics,
+    retry_policy: RetryPolicy,
+    builder: Option<ClientBuilder>,
+}
+
+/// Fail to compile unless the client and its prepared statements can be
+/// cloned and shared between tasks.
+fn assert_send_sync<T: Send + Sync + Clone>() {}
+
+const _: fn() = || {
+    assert_send_sync::<FlightSqlServiceClient>();
+    assert_send_sync::<PreparedStatement>();
+};
+
+impl FlightSqlServiceClient {
+    /// Connect to the server at the uri, such as `grpc+tls://flight.example.com:443`.
+    /// See `ClientBuilder::from_uri` for the supported schemes and parameters.
+    #[tracing::instrument(skip_all)]
+    pub async fn connect(uri: &str) -> Result<Self> {
+        ClientBuilder::from_uri(uri)?.connect().await
+    }
+
+    /// create FlightSqlServiceClient using a Channel
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn new(channel: Channel) -> Self {
+        let headers = HeaderInterceptor::default();
+        FlightSqlServiceClient {
+            inner: FlightServiceClient::with_interceptor(channel, headers.clone()),
+            headers,
+            metrics: ClientMetrics::default(),
+            retry_policy: RetryPolicy::none(),
+            builder: None,
+        }
+    }
+
+    /// Retry the metadata requests and DoGet calls with the policy when the
+    /// server is unavailable. Nothing is retried by default.
     #[tracing::instrument(level = "debug", skip_all)]
-    pub fn new(client: RefCell<FlightServiceClient<T>>) -> Self {
-        FlightSqlServiceClient { inner: client }
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
     }
 
-    /// borrow mut FlightServiceClient
+    /// Connect to the locations of endpoints with the settings of the
+    /// builder, see `ClientBuilder::for_location`.
     #[tracing::instrument(level = "debug", skip_all)]
-    fn mut_client(&self) -> RefMut<'_, FlightServiceClient<T>> {
-        self.inner.borrow_mut()
+    pub(crate) fn with_builder(mut self, builder: ClientBuilder) -> Self {
+        self.builder = Some(builder);
+        self
     }
 
+    /// clone FlightServiceClient, sharing the Channel of this client
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn client(&self) -> InterceptedClient {
+        self.inner.clone()
+    }
+
+    /// Authenticate with a username and password using the Handshake RPC.
+    ///
+    /// The credentials are sent both as a `Basic` authorization header and as
+    /// a BasicAuth payload. The bearer token returned by the server, in the
+    /// `authorization` header or else in the payload of the response, is sent
+    /// with every later request and returned.
     #[tracing::instrument(skip_all)]
-    async fn get_flight_info_for_command<M: ProstMessageExt>(
-        &mut self,
-        cmd: M,
-    ) -> Result<FlightInfo> {
+    pub async fn handshake(&self, username: &str, password: &str) -> Result<String> {
+        let payload = BasicAuth {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+        .encode_to_vec();
+        let handshake_request = HandshakeRequest {
+            protocol_version: 0,
+            payload,
+        };
+        let span = rpc_span("Handshake", handshake_request.encoded_len());
+        let mut request = tonic::Request::new(stream::iter(vec![handshake_request]));
+        let credentials = base64::encode(format!("{}:{}", username, password));
+        request.metadata_mut().insert(
+            AUTHORIZATION_HEADER,
+            format!("Basic {}", credentials).parse().map_err(|_| {
+                FlightSqlError::InvalidArgument("Invalid username or password".to_string())
+            })?,
+        );
+
+        let response = self
+            .client()
+            .handshake(request)
+            .instrument(span.clone())
+            .await;
+        record_status(&span, &response);
+        self.metrics.record_status("Handshake", &response);
+        let response = response?;
+        let mut token = response
+            .metadata()
+            .get(AUTHORIZATION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(bearer_token)
+            .map(str::to_string);
+        let mut responses = response.into_inner();
+        let mut response_size = 0;
+        while let Some(response) = responses.message().instrument(span.clone()).await? {
+            response_size += response.encoded_len();
+            if token.is_none() && !response.payload.is_empty() {
+                token = String::from_utf8(response.payload).ok();
+            }
+        }
+        record_response(&span, response_size);
+
+        let token = token.ok_or_else(|| {
+            FlightSqlError::Protocol("The server did not return a bearer token".to_string())
+        })?;
+        self.set_token(&token)?;
+        Ok(token)
+    }
+
+    /// Send the bearer token with every request.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn set_token(&self, token: &str) -> Result<()> {
+        self.headers.set_token(token)
+    }
+
+    /// The bearer token that is sent with every request.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn token(&self) -> Option<String> {
+        self.headers.token()
+    }
+
+    /// Send the header with every request.
+    #[tracing::instrument(level = "debug", skip(self, value))]
+    pub fn set_header(&self, key: &str, value: &str) -> Result<()> {
+        self.headers.insert(key, value)
+    }
+
+    /// Send the timeout as the `grpc-timeout` deadline of every request, or
+    /// no deadline when None.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.headers.set_timeout(timeout)
+    }
+
+    /// The stats of the calls made by this client and its clones, since the
+    /// stats were last taken.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn stats(&self) -> ClientStats {
+        self.metrics.stats()
+    }
+
+    /// Take the stats of the calls made so far and start over, such as to
+    /// report the stats of each query.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn take_stats(&self) -> ClientStats {
+        self.metrics.take_stats()
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_flight_info_for_command<M: ProstMessageExt>(&self, cmd: M) -> Result<FlightInfo> {
         let descriptor = FlightDescriptor::new_cmd(cmd.as_any().encode_to_vec());
-        Ok(self
-            .mut_client()
-            .get_flight_info(descriptor)
-            .await
-            .map_err(status_to_arrow_error)?
-            .into_inner())
+        get_flight_info(self.client(), &self.metrics, descriptor).await
+    }
+
+    /// Request the FlightInfo of a metadata command, which is safe to repeat
+    /// and so retried with the retry policy.
+    #[tracing::instrument(skip_all)]
+    async fn get_flight_info_for_metadata<M: ProstMessageExt>(&self, cmd: M) -> Result<FlightInfo> {
+        let descriptor = FlightDescriptor::new_cmd(cmd.as_any().encode_to_vec());
+        retry(&self.retry_policy, "GetFlightInfo", || {
+            get_flight_info(self.client(), &self.metrics, descriptor.clone())
+        })
+        .await
     }
 
     /// Execute a query on the server.
     #[tracing::instrument(skip_all)]
-    pub async fn execute(&mut self, query: String) -> Result<FlightInfo> {
+    pub async fn execute(&self, query: String) -> Result<FlightInfo> {
         let cmd = CommandStatementQuery { query };
         self.get_flight_info_for_command(cmd).await
     }
 
     /// Execute a update query on the server.
     #[tracing::instrument(skip_all)]
-    pub async fn execute_update(&mut self, query: String) -> Result<i64> {
+    pub async fn execute_update(&self, query: String) -> Result<i64> {
         let cmd = CommandStatementUpdate { query };
         let descriptor = FlightDescriptor::new_cmd(cmd.as_any().encode_to_vec());
-        let mut result = self
-            .mut_client()
-            .do_put(stream::iter(vec![FlightData {
-                flight_descriptor: Some(descriptor),
-                ..Default::default()
-            }]))
-            .await
-            .map_err(status_to_arrow_error)?
-            .into_inner();
-        let result = result
-            .message()
-            .await
-            .map_err(status_to_arrow_error)?
-            .unwrap();
-        let any: prost_types::Any =
-            prost::Message::decode(&*result.app_metadata).map_err(decode_error_to_arrow_error)?;
-        let result: DoPutUpdateResult = any.unpack()?.unwrap();
+        let flight_data = vec![FlightData {
+            flight_descriptor: Some(descriptor),
+            ..Default::default()
+        }];
+        let result = do_put(self.client(), &self.metrics, flight_data, 0).await?;
+        let result = result.into_iter().next().ok_or_else(|| {
+            FlightSqlError::Protocol(
+                "The server did not return the result of the update".to_string(),
+            )
+        })?;
+        let result: DoPutUpdateResult = decode_any(&result.app_metadata)?;
         Ok(result.record_count)
     }
 
     /// Request a list of catalogs.
     #[tracing::instrument(skip_all)]
-    pub async fn get_catalogs(&mut self) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(CommandGetCatalogs {})
+    pub async fn get_catalogs(&self) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(CommandGetCatalogs {})
             .await
     }
 
     /// Request a list of database schemas.
     #[tracing::instrument(skip_all)]
-    pub async fn get_db_schemas(&mut self, request: CommandGetDbSchemas) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(request).await
+    pub async fn get_db_schemas(&self, request: CommandGetDbSchemas) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(request).await
     }
 
     /// Given a flight ticket and schema, request to be sent the
     /// stream. Returns record batch stream reader
     #[tracing::instrument(skip_all)]
-    pub async fn do_get(&mut self, ticket: Ticket) -> Result<Streaming<FlightData>> {
-        Ok(self
-            .mut_client()
-            .do_get(ticket)
-            .await
-            .map_err(status_to_arrow_error)?
-            .into_inner())
+    pub async fn do_get(&self, ticket: Ticket) -> Result<Streaming<FlightData>> {
+        let call = retry(&self.retry_policy, "DoGet", || {
+            do_get(self.client(), &self.metrics, ticket.clone())
+        })
+        .await?;
+        Ok(call.stream)
+    }
+
+    /// Retrieve the data of all the endpoints in a FlightInfo, one after the
+    /// other, as a stream of RecordBatches.
+    #[tracing::instrument(skip_all)]
+    pub async fn do_get_stream(&self, fi: &FlightInfo) -> Result<RecordBatchStream> {
+        self.do_get_endpoints(fi, &DoGetOptions::default()).await
+    }
+
+    /// Retrieve the data of all the endpoints in a FlightInfo as a stream of
+    /// RecordBatches, reading up to `options.concurrency` endpoints at a time.
+    ///
+    /// An endpoint that lists locations is read from a new connection to the
+    /// first location that can be reached, other endpoints are read over the
+    /// connection of this client.
+    #[tracing::instrument(skip_all)]
+    pub async fn do_get_endpoints(
+        &self,
+        fi: &FlightInfo,
+        options: &DoGetOptions,
+    ) -> Result<RecordBatchStream> {
+        let arrow_schema_ref = SchemaRef::new(arrow_schema_from_flight_info(fi)?);
+        let client = self.client();
+        let headers = self.headers.clone();
+        let metrics = self.metrics.clone();
+        let retry_policy = self.retry_policy.clone();
+        let builder = self.builder.clone();
+        let idle_timeout = options.idle_timeout;
+
+        let streams = stream::iter(fi.endpoint.clone()).map(move |endpoint| {
+            let client = client.clone();
+            let headers = headers.clone();
+            let metrics = metrics.clone();
+            let retry_policy = retry_policy.clone();
+            let builder = builder.clone();
+            let arrow_schema_ref = arrow_schema_ref.clone();
+            async move {
+                let call = do_get_endpoint(
+                    client,
+                    headers,
+                    builder.as_ref(),
+                    &metrics,
+                    &retry_policy,
+                    endpoint,
+                )
+                .await?;
+                let record_batch_stream = traced_record_batch_stream(arrow_schema_ref, call);
+                Ok::<_, FlightSqlError>(match idle_timeout {
+                    Some(idle_timeout) => with_idle_timeout(record_batch_stream, idle_timeout),
+                    None => record_batch_stream,
+                })
+            }
+        });
+
+        let concurrency = options.concurrency.max(1);
+        if options.ordered {
+            Ok(streams.buffered(concurrency).try_flatten().boxed())
+        } else {
+            Ok(streams
+                .map(|flight_data_stream| stream::once(flight_data_stream).try_flatten().boxed())
+                .flatten_unordered(concurrency)
+                .boxed())
+        }
+    }
+
+    /// Retrieve the data of all the endpoints in a FlightInfo as a vector of
+    /// RecordBatches.
+    #[tracing::instrument(skip_all)]
+    pub async fn do_get_batches(&self, fi: &FlightInfo) -> Result<Vec<RecordBatch>> {
+        self.do_get_stream(fi).await?.try_collect().await
+    }
+
+    /// Execute a query on the server and return its results as a stream of
+    /// RecordBatches.
+    #[tracing::instrument(skip_all)]
+    pub async fn execute_stream(&self, query: String) -> Result<RecordBatchStream> {
+        let fi = self.execute(query).await?;
+        self.do_get_stream(&fi).await
+    }
+
+    /// Execute a query on the server and collect its results.
+    #[tracing::instrument(skip_all)]
+    pub async fn execute_batches(&self, query: String) -> Result<Vec<RecordBatch>> {
+        let fi = self.execute(query).await?;
+        self.do_get_batches(&fi).await
     }
 
     /// Request a list of tables.
     #[tracing::instrument(skip_all)]
-    pub async fn get_tables(&mut self, request: CommandGetTables) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(request).await
+    pub async fn get_tables(&self, request: CommandGetTables) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(request).await
     }
 
     /// Request the primary keys for a table.
     #[tracing::instrument(skip_all)]
-    pub async fn get_primary_keys(&mut self, request: CommandGetPrimaryKeys) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(request).await
+    pub async fn get_primary_keys(&self, request: CommandGetPrimaryKeys) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(request).await
     }
 
     /// Retrieves a description about the foreign key columns that reference the
     /// primary key columns of the given table.
     #[tracing::instrument(skip_all)]
-    pub async fn get_exported_keys(
-        &mut self,
-        request: CommandGetExportedKeys,
-    ) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(request).await
+    pub async fn get_exported_keys(&self, request: CommandGetExportedKeys) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(request).await
     }
 
     /// Retrieves the foreign key columns for the given table.
     #[tracing::instrument(skip_all)]
-    pub async fn get_imported_keys(
-        &mut self,
-        request: CommandGetImportedKeys,
-    ) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(request).await
+    pub async fn get_imported_keys(&self, request: CommandGetImportedKeys) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(request).await
     }
 
     /// Retrieves a description of the foreign key columns in the given foreign key
@@ -156,54 +389,80 @@ where
     /// constraint of the parent table (could be the same or a different table).
     #[tracing::instrument(skip_all)]
     pub async fn get_cross_reference(
-        &mut self,
+        &self,
         request: CommandGetCrossReference,
     ) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(request).await
+        self.get_flight_info_for_metadata(request).await
     }
 
     /// Request a list of table types.
     #[tracing::instrument(skip_all)]
-    pub async fn get_table_types(&mut self) -> Result<FlightInfo> {
-        self.get_flight_info_for_command(CommandGetTableTypes {})
+    pub async fn get_table_types(&self) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(CommandGetTableTypes {})
             .await
     }
 
     /// Request a list of SQL information.
     #[tracing::instrument(skip_all)]
-    pub async fn get_sql_info(&mut self, sql_infos: Vec<SqlInfo>) -> Result<FlightInfo> {
+    pub async fn get_sql_info(&self, sql_infos: Vec<SqlInfo>) -> Result<FlightInfo> {
         let request = CommandGetSqlInfo {
             info: sql_infos.iter().map(|sql_info| *sql_info as u32).collect(),
         };
-        self.get_flight_info_for_command(request).await
+        self.get_flight_info_for_metadata(request).await
+    }
+
+    /// Request and decode the values of the given SqlInfos, or of all SqlInfos
+    /// the server supports when none are given.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_sql_info_values(
+        &self,
+        sql_infos: Vec<SqlInfo>,
+    ) -> Result<Vec<(u32, SqlInfoValue)>> {
+        let fi = self.get_sql_info(sql_infos).await?;
+        let mut infos = vec![];
+        for batch in self.do_get_batches(&fi).await? {
+            infos.extend(decode_sql_info(&batch)?);
+        }
+        Ok(infos)
+    }
+
+    /// Request the values of all SqlInfos and decode them into the
+    /// capabilities of the server.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_server_capabilities(&self) -> Result<ServerCapabilities> {
+        let infos = self.get_sql_info_values(vec![]).await?;
+        Ok(ServerCapabilities::from_sql_info(
+            infos.into_iter().collect(),
+        ))
+    }
+
+    /// Request information about the data types supported by the server,
+    /// optionally only for the given data type.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_xdbc_type_info(&self, request: CommandGetXdbcTypeInfo) -> Result<FlightInfo> {
+        self.get_flight_info_for_metadata(request).await
     }
 
     /// Create a prepared statement object.
     #[tracing::instrument(skip_all)]
-    pub async fn prepare(&mut self, query: String) -> Result<PreparedStatement<'_, T>> {
+    pub async fn prepare(&self, query: String) -> Result<PreparedStatement> {
         let cmd = ActionCreatePreparedStatementRequest { query };
         let action = Action {
             r#type: ACTION_TYPE_CREATE_PREPARED_STATEMENT.to_string(),
             body: cmd.as_any().encode_to_vec(),
         };
-        let mut result = self
-            .mut_client()
-            .do_action(tonic::Request::new(action))
-            .await
-            .map_err(status_to_arrow_error)?
-            .into_inner();
-        let result = result
-            .message()
-            .await
-            .map_err(status_to_arrow_error)?
-            .unwrap();
-        let any: prost_types::Any =
-            prost::Message::decode(&*result.body).map_err(decode_error_to_arrow_error)?;
-        let prepared_result: ActionCreatePreparedStatementResult = any.unpack()?.unwrap();
-        let dataset_schema = Schema::try_from(IpcMessage(prepared_result.dataset_schema))?;
-        let parameter_schema = Schema::try_from(IpcMessage(prepared_result.parameter_schema))?;
+        let result = do_action(self.client(), &self.metrics, action).await?;
+        let result = result.into_iter().next().ok_or_else(|| {
+            FlightSqlError::Protocol("The server did not return the prepared statement".to_string())
+        })?;
+        let prepared_result: ActionCreatePreparedStatementResult = decode_any(&result.body)?;
+        // The schemas are left empty by servers when the query returns no
+        // results or has no parameters.
+        let dataset_schema = decode_optional_schema(prepared_result.dataset_schema)?;
+        let parameter_schema = decode_optional_schema(prepared_result.parameter_schema)?;
         Ok(PreparedStatement::new(
-            &self.inner,
+            self.client(),
+            self.metrics.clone(),
             prepared_result.prepared_statement_handle,
             dataset_schema,
             parameter_schema,
@@ -217,99 +476,319 @@ where
     }
 }
 
+/// The options used to read the endpoints of a FlightInfo.
+#[derive(Debug, Clone)]
+pub struct DoGetOptions {
+    /// The maximum number of endpoints that are read at the same time.
+    pub concurrency: usize,
+    /// Return the batches in the order of the endpoints, instead of as soon
+    /// as they arrive from any endpoint.
+    pub ordered: bool,
+    /// Fail when an endpoint sends no batch within this time of the moment
+    /// the next batch is read, however long the whole stream takes.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for DoGetOptions {
+    fn default() -> Self {
+        DoGetOptions {
+            concurrency: 1,
+            ordered: true,
+            idle_timeout: None,
+        }
+    }
+}
+
+/// The uri of a Location telling the client to redeem the ticket over the
+/// connection it used to get the FlightInfo.
+pub static LOCATION_REUSE_CONNECTION: &str = "arrow-flight-reuse-connection://?";
+
+/// Send the ticket of the endpoint to one of its locations, or to the given
+/// client when it has none. The locations are connected to with the settings
+/// of the builder of the client, when it has one, and the headers of the
+/// client, such as the bearer token, are also sent to them. The ticket is
+/// sent again with the retry policy while the server is unavailable, but not
+/// once the stream started.
+#[tracing::instrument(skip_all)]
+async fn do_get_endpoint(
+    client: InterceptedClient,
+    headers: HeaderInterceptor,
+    builder: Option<&ClientBuilder>,
+    metrics: &ClientMetrics,
+    retry_policy: &RetryPolicy,
+    endpoint: FlightEndpoint,
+) -> Result<DoGetCall> {
+    let ticket = endpoint.ticket.ok_or_else(|| {
+        FlightSqlError::Protocol("Failed to get ticket from endpoint".to_string())
+    })?;
+
+    let client = if endpoint.location.is_empty()
+        || endpoint
+            .location
+            .iter()
+            .any(|location| location.uri == LOCATION_REUSE_CONNECTION)
+    {
+        client
+    } else {
+        connect_to_locations(&endpoint.location, builder, headers).await?
+    };
+
+    retry(retry_policy, "DoGet", || {
+        do_get(client.clone(), metrics, ticket.clone())
+    })
+    .await
+}
+
+/// Call GetFlightInfo in its own span.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn get_flight_info(
+    mut client: InterceptedClient,
+    metrics: &ClientMetrics,
+    descriptor: FlightDescriptor,
+) -> Result<FlightInfo> {
+    let span = rpc_span("GetFlightInfo", descriptor.encoded_len());
+    let started = Instant::now();
+    let result = client
+        .get_flight_info(descriptor)
+        .instrument(span.clone())
+        .await;
+    metrics.record_get_flight_info(started.elapsed());
+    record_status(&span, &result);
+    metrics.record_status("GetFlightInfo", &result);
+    let fi = result?.into_inner();
+    record_response(&span, fi.encoded_len());
+    Ok(fi)
+}
+
+/// A DoGet call whose stream is read, with the span and the metrics its
+/// batches are recorded in, and the moment it was made.
+struct DoGetCall {
+    stream: Streaming<FlightData>,
+    span: Span,
+    metrics: ClientMetrics,
+    started: Instant,
+}
+
+/// Call DoGet in its own span, which is returned to record the batches of
+/// the stream as they are read.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn do_get(
+    mut client: InterceptedClient,
+    metrics: &ClientMetrics,
+    ticket: Ticket,
+) -> Result<DoGetCall> {
+    let span = rpc_span("DoGet", ticket.encoded_len());
+    let started = Instant::now();
+    let mut request = tonic::Request::new(ticket);
+    request.extensions_mut().insert(WithoutDeadline);
+    let result = client.do_get(request).instrument(span.clone()).await;
+    record_status(&span, &result);
+    metrics.record_status("DoGet", &result);
+    // Only the attempt that opens the stream is counted, a failed one is
+    // retried or ends the call.
+    let stream = result?.into_inner();
+    metrics.record_do_get();
+    Ok(DoGetCall {
+        stream,
+        span,
+        metrics: metrics.clone(),
+        started,
+    })
+}
+
+/// Call DoPut in its own span with the messages, which carry `records`
+/// parameter rows, and collect the results.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn do_put(
+    mut client: InterceptedClient,
+    metrics: &ClientMetrics,
+    flight_data: Vec<FlightData>,
+    records: usize,
+) -> Result<Vec<PutResult>> {
+    let request_size = flight_data.iter().map(Message::encoded_len).sum();
+    let span = rpc_span("DoPut", request_size);
+    span.record("flight.records", records);
+    let result = async {
+        let mut request = tonic::Request::new(stream::iter(flight_data));
+        request.extensions_mut().insert(WithoutDeadline);
+        let mut results = client.do_put(request).await?.into_inner();
+        let mut collected = vec![];
+        while let Some(result) = results.message().await? {
+            collected.push(result);
+        }
+        Ok(collected)
+    }
+    .instrument(span.clone())
+    .await;
+    record_status(&span, &result);
+    metrics.record_status("DoPut", &result);
+    let results = result?;
+    record_response(&span, results.iter().map(Message::encoded_len).sum());
+    Ok(results)
+}
+
+/// Call DoAction in its own span and collect the results.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn do_action(
+    mut client: InterceptedClient,
+    metrics: &ClientMetrics,
+    action: Action,
+) -> Result<Vec<crate::arrow_flight_protocol::Result>> {
+    let span = rpc_span("DoAction", action.encoded_len());
+    let result = async {
+        let mut results = client.do_action(action).await?.into_inner();
+        let mut collected = vec![];
+        while let Some(result) = results.message().await? {
+            collected.push(result);
+        }
+        Ok(collected)
+    }
+    .instrument(span.clone())
+    .await;
+    record_status(&span, &result);
+    metrics.record_status("DoAction", &result);
+    let results = result?;
+    record_response(&span, results.iter().map(Message::encoded_len).sum());
+    Ok(results)
+}
+
+/// Connect to the first of the locations that can be reached.
+#[tracing::instrument(skip_all)]
+async fn connect_to_locations(
+    locations: &[Location],
+    builder: Option<&ClientBuilder>,
+    headers: HeaderInterceptor,
+) -> Result<InterceptedClient> {
+    let mut last_error = None;
+    for location in locations {
+        match connect_to_location(location, builder, headers.clone()).await {
+            Ok(client) => return Ok(client),
+            Err(err) => {
+                tracing::warn!("Failed to connect to location {}: {}", location.uri, err);
+                last_error = Some(err);
+            }
+        }
+    }
+    Err(last_error
+        .unwrap_or_else(|| FlightSqlError::Protocol("Endpoint has no locations".to_string())))
+}
+
+/// Connect to the location, with the settings of the builder when given.
+/// The parameters of the location uri apply on top of them.
+#[tracing::instrument(skip_all, fields(uri = %location.uri))]
+async fn connect_to_location(
+    location: &Location,
+    builder: Option<&ClientBuilder>,
+    headers: HeaderInterceptor,
+) -> Result<InterceptedClient> {
+    let location_builder = ClientBuilder::from_uri(&location.uri)?;
+    let channel = match builder {
+        Some(builder) => builder.for_location(&location.uri)?,
+        None => location_builder.clone(),
+    }
+    .connect_channel()
+    .await?;
+    let headers = location_builder.location_headers(headers)?;
+    Ok(FlightServiceClient::with_interceptor(channel, headers))
+}
+
 /// A PreparedStatement
 #[derive(Debug, Clone)]
-pub struct PreparedStatement<'a, T> {
-    inner: &'a RefCell<FlightServiceClient<T>>,
+pub struct PreparedStatement {
+    inner: InterceptedClient,
+    metrics: ClientMetrics,
     is_closed: bool,
-    parameter_binding: Option<RecordBatch<'a>>,
+    parameter_binding: Vec<RecordBatch>,
     handle: Vec<u8>,
     dataset_schema: Schema,
     parameter_schema: Schema,
 }
 
-impl<'a, T> PreparedStatement<'a, T>
-where
-    T: tonic::client::GrpcService<tonic::body::BoxBody>,
-    T::Error: Into<StdError>,
-    T::ResponseBody: Default + Body<Data = bytes::Bytes> + Send + 'static,
-    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
-{
+impl PreparedStatement {
     #[tracing::instrument(level = "debug", skip_all)]
     pub(crate) fn new(
-        client: &'a RefCell<FlightServiceClient<T>>,
+        client: InterceptedClient,
+        metrics: ClientMetrics,
         handle: Vec<u8>,
         dataset_schema: Schema,
         parameter_schema: Schema,
     ) -> Self {
         PreparedStatement {
             inner: client,
+            metrics,
             is_closed: false,
-            parameter_binding: None,
+            parameter_binding: vec![],
             handle,
             dataset_schema,
             parameter_schema,
         }
     }
     /// Executes the prepared statement query on the server.
+    ///
+    /// When parameters were bound with `set_parameters`, they are first sent
+    /// to the server with DoPut before the FlightInfo is requested.
     #[tracing::instrument(skip_all)]
-    pub async fn execute(&mut self) -> Result<FlightInfo> {
+    pub async fn execute(&self) -> Result<FlightInfo> {
         if self.is_closed() {
-            return Err(ArrowError::IoError("Statement already closed.".to_string()));
+            return Err(FlightSqlError::InvalidArgument(
+                "Statement already closed.".to_string(),
+            ));
         }
         let cmd = CommandPreparedStatementQuery {
             prepared_statement_handle: self.handle.clone(),
         };
         let descriptor = FlightDescriptor::new_cmd(cmd.as_any().encode_to_vec());
-        let mut result = self
-            .mut_client()
-            .do_put(stream::iter(vec![FlightData {
-                flight_descriptor: Some(descriptor),
-                ..Default::default()
-            }]))
-            .await
-            .map_err(status_to_arrow_error)?
-            .into_inner();
-        let result = result
-            .message()
-            .await
-            .map_err(status_to_arrow_error)?
-            .unwrap();
-        let _: prost_types::Any =
-            prost::Message::decode(&*result.app_metadata).map_err(decode_error_to_arrow_error)?;
-        Err(ArrowError::NotYetImplemented(
-            "Not yet implemented".to_string(),
-        ))
+
+        if !self.parameter_binding.is_empty() {
+            self.do_put_parameters(descriptor.clone()).await?;
+        }
+
+        get_flight_info(self.client(), &self.metrics, descriptor).await
     }
 
     /// Executes the prepared statement update query on the server.
+    ///
+    /// All bound parameter batches are sent in a single DoPut stream, so the
+    /// server executes the update once per parameter row as one atomic operation.
     #[tracing::instrument(skip_all)]
     pub async fn execute_update(&self) -> Result<i64> {
         if self.is_closed() {
-            return Err(ArrowError::IoError("Statement already closed.".to_string()));
+            return Err(FlightSqlError::InvalidArgument(
+                "Statement already closed.".to_string(),
+            ));
         }
-        let cmd = CommandPreparedStatementQuery {
+        let cmd = CommandPreparedStatementUpdate {
             prepared_statement_handle: self.handle.clone(),
         };
         let descriptor = FlightDescriptor::new_cmd(cmd.as_any().encode_to_vec());
-        let mut result = self
-            .mut_client()
-            .do_put(stream::iter(vec![FlightData {
+        let result = self.do_put_parameters(descriptor).await?;
+        let result = result.into_iter().next().ok_or_else(|| {
+            FlightSqlError::Protocol(
+                "The server did not return the result of the update".to_string(),
+            )
+        })?;
+        let result: DoPutUpdateResult = decode_any(&result.app_metadata)?;
+        Ok(result.record_count)
+    }
+
+    /// Send the bound parameters with DoPut, or only the descriptor when no
+    /// parameters were bound.
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn do_put_parameters(&self, descriptor: FlightDescriptor) -> Result<Vec<PutResult>> {
+        let flight_data = if self.parameter_binding.is_empty() {
+            vec![FlightData {
                 flight_descriptor: Some(descriptor),
                 ..Default::default()
-            }]))
-            .await
-            .map_err(status_to_arrow_error)?
-            .into_inner();
-        let result = result
-            .message()
-            .await
-            .map_err(status_to_arrow_error)?
-            .unwrap();
-        let any: prost_types::Any =
-            prost::Message::decode(&*result.app_metadata).map_err(decode_error_to_arrow_error)?;
-        let result: DoPutUpdateResult = any.unpack()?.unwrap();
-        Ok(result.record_count)
+            }]
+        } else {
+            flight_data_from_arrow_batches(descriptor, &self.parameter_binding)?
+        };
+        let records = self
+            .parameter_binding
+            .iter()
+            .map(RecordBatch::num_rows)
+            .sum();
+        do_put(self.client(), &self.metrics, flight_data, records).await
     }
 
     /// Retrieve the parameter schema from the query.
@@ -326,8 +805,28 @@ where
 
     /// Set a RecordBatch that contains the parameters that will be bind.
     #[tracing::instrument(level = "debug", skip_all)]
-    pub async fn set_parameters(&mut self, parameter_binding: RecordBatch<'a>) -> Result<()> {
-        self.parameter_binding = Some(parameter_binding);
+    pub async fn set_parameters(&mut self, parameter_binding: RecordBatch) -> Result<()> {
+        self.parameter_binding = vec![parameter_binding];
+        Ok(())
+    }
+
+    /// Set several RecordBatches with parameters that will be bind. Every row
+    /// is a parameter set, and all of them are sent to the server in one call.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn set_parameter_batches(
+        &mut self,
+        parameter_binding: Vec<RecordBatch>,
+    ) -> Result<()> {
+        if let Some(batch) = parameter_binding
+            .iter()
+            .find(|batch| batch.schema() != parameter_binding[0].schema())
+        {
+            return Err(FlightSqlError::InvalidArgument(format!(
+                "All parameter batches must share the same schema, found {:?}",
+                batch.schema()
+            )));
+        }
+        self.parameter_binding = parameter_binding;
         Ok(())
     }
 
@@ -336,7 +835,9 @@ where
     #[tracing::instrument(skip_all)]
     pub async fn close(&mut self) -> Result<()> {
         if self.is_closed() {
-            return Err(ArrowError::IoError("Statement already closed.".to_string()));
+            return Err(FlightSqlError::InvalidArgument(
+                "Statement already closed.".to_string(),
+            ));
         }
         let cmd = ActionClosePreparedStatementRequest {
             prepared_statement_handle: self.handle.clone(),
@@ -345,11 +846,7 @@ where
             r#type: ACTION_TYPE_CLOSE_PREPARED_STATEMENT.to_string(),
             body: cmd.as_any().encode_to_vec(),
         };
-        let _ = self
-            .mut_client()
-            .do_action(action)
-            .await
-            .map_err(status_to_arrow_error)?;
+        do_action(self.client(), &self.metrics, action).await?;
         self.is_closed = true;
         Ok(())
     }
@@ -360,16 +857,16 @@ where
         self.is_closed
     }
 
-    /// borrow mut FlightServiceClient
+    /// clone FlightServiceClient, sharing the Channel of the statement
     #[tracing::instrument(level = "debug", skip_all)]
-    fn mut_client(&self) -> RefMut<'_, FlightServiceClient<T>> {
-        self.inner.borrow_mut()
+    fn client(&self) -> InterceptedClient {
+        self.inner.clone()
     }
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
 pub fn decode_error_to_arrow_error(err: prost::DecodeError) -> ArrowError {
-    ArrowError::IoError(err.to_string())
+    FlightSqlError::from(err).into()
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -379,108 +876,392 @@ pub fn arrow_error_to_status(err: arrow::error::ArrowError) -> tonic::Status {
 
 #[tracing::instrument(level = "debug", skip_all)]
 pub fn status_to_arrow_error(status: tonic::Status) -> ArrowError {
-    ArrowError::IoError(format!("{:?}", status))
+    FlightSqlError::from(status).into()
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
 pub fn transport_error_to_arrow_erorr(error: tonic::transport::Error) -> ArrowError {
-    ArrowError::IoError(format!("{}", error))
+    FlightSqlError::from(error).into()
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn arrow_schema_from_flight_info(fi: &FlightInfo) -> ArrowResult<Schema> {
+    decode_ipc_schema(&fi.schema)
+}
+
+/// The marker that precedes the length of an encapsulated IPC message, which
+/// is missing in the legacy format.
+const CONTINUATION_MARKER: [u8; 4] = [0xff; 4];
+
+/// Decode a Schema from an encapsulated IPC message, such as the schema of a
+/// FlightInfo.
+#[tracing::instrument(level = "debug", skip_all)]
+fn decode_ipc_schema(bytes: &[u8]) -> ArrowResult<Schema> {
+    let bytes = bytes
+        .strip_prefix(&CONTINUATION_MARKER[..])
+        .unwrap_or(bytes);
+    let (length, message) = match bytes {
+        [a, b, c, d, message @ ..] => (i32::from_le_bytes([*a, *b, *c, *d]), message),
+        _ => {
+            return Err(ArrowError::ParseError(format!(
+                "Unable to read the length of a schema message of {} bytes",
+                bytes.len()
+            )))
+        }
+    };
+    let message = usize::try_from(length)
+        .ok()
+        .and_then(|length| message.get(..length))
+        .ok_or_else(|| {
+            ArrowError::ParseError(format!(
+                "Schema message of {} bytes is shorter than its length {}",
+                message.len(),
+                length
+            ))
+        })?;
+    let message = root_as_message(message).map_err(|err| {
+        ArrowError::ParseError(format!("Unable to read the schema message: {}", err))
+    })?;
+    let ipc_schema = message.header_as_schema().ok_or_else(|| {
+        ArrowError::ParseError(format!(
+            "Expected a schema message, found {:?}",
+            message.header_type()
+        ))
+    })?;
+    validate_schema(ipc_schema)?;
+    Ok(convert::fb_to_schema(ipc_schema))
+}
+
+/// Decode a Schema that may be left empty, in which case it has no fields.
+#[tracing::instrument(level = "debug", skip_all)]
+fn decode_optional_schema(bytes: Vec<u8>) -> ArrowResult<Schema> {
+    if bytes.is_empty() {
+        Ok(Schema::empty())
+    } else {
+        Schema::try_from(IpcMessage(bytes))
+    }
+}
+
+/// Decode a message packed in a protobuf Any, as returned in the body of the
+/// results of actions and in the metadata of the results of DoPut.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn decode_any<M: ProstMessageExt>(bytes: &[u8]) -> Result<M> {
+    let any: prost_types::Any = prost::Message::decode(bytes)?;
+    any.unpack()?.ok_or_else(|| {
+        FlightSqlError::Protocol(format!(
+            "Expected a {} message, found {}",
+            M::type_url(),
+            any.type_url
+        ))
+    })
 }
 
+/// Decode a stream of `FlightData` into the RecordBatches it contains.
 #[tracing::instrument(level = "debug", skip_all)]
-pub fn arrow_schema_from_flight_info(fi: &FlightInfo) -> Result<Schema> {
-    let ipc_message = arrow::ipc::size_prefixed_root_as_message(&fi.schema[4..])
-        .map_err(|e| ArrowError::ComputeError(format!("{:?}", e)))?;
+pub fn record_batch_stream(
+    arrow_schema_ref: SchemaRef,
+    flight_data_stream: Streaming<FlightData>,
+) -> RecordBatchStream {
+    let call = DoGetCall {
+        stream: flight_data_stream,
+        span: Span::none(),
+        metrics: ClientMetrics::default(),
+        started: Instant::now(),
+    };
+    traced_record_batch_stream(arrow_schema_ref, call)
+}
 
-    let ipc_schema = ipc_message
-        .header_as_schema()
-        .ok_or(ArrowError::ComputeError(
-            "failed to get schema...".to_string(),
-        ))?;
+/// Decode the stream like `record_batch_stream`, recording the status of the
+/// stream, the size of its messages and the batches and records decoded in
+/// the span and the metrics of the DoGet call.
+fn traced_record_batch_stream(arrow_schema_ref: SchemaRef, call: DoGetCall) -> RecordBatchStream {
+    let DoGetCall {
+        stream: flight_data_stream,
+        span,
+        metrics,
+        started,
+    } = call;
+    let mut decoder = FlightDataDecoder::new(arrow_schema_ref);
+    let (mut response_size, mut batches, mut records) = (0, 0, 0);
+    let (batch_span, batch_metrics, end_metrics) = (span.clone(), metrics.clone(), metrics.clone());
+    flight_data_stream
+        // Mark the end of the stream, to record how long it took to read.
+        .map(Some)
+        .chain(stream::once(future::ready(None)))
+        .filter_map(move |flight_data| {
+            if flight_data.is_none() {
+                end_metrics.record_do_get_end(started.elapsed());
+            }
+            future::ready(flight_data)
+        })
+        .inspect(move |flight_data| match flight_data {
+            Ok(flight_data) => {
+                response_size += flight_data.encoded_len();
+                record_response(&span, response_size);
+                metrics.record_message(flight_data.encoded_len());
+            }
+            Err(_) => {
+                record_status(&span, flight_data);
+                metrics.record_status("DoGet", flight_data);
+            }
+        })
+        .map_err(FlightSqlError::from)
+        .try_filter_map(move |flight_data| {
+            future::ready(
+                decoder
+                    .decode(flight_data)
+                    .map(|arrow_data| match arrow_data {
+                        ArrowFlightData::RecordBatch(record_batch) => Some(record_batch),
+                        _ => None,
+                    })
+                    .map_err(FlightSqlError::from),
+            )
+        })
+        .inspect_ok(move |record_batch| {
+            let first_batch_after = (batches == 0).then(|| started.elapsed());
+            batches += 1;
+            records += record_batch.num_rows();
+            batch_span.record("flight.batches", batches);
+            batch_span.record("flight.records", records);
+            batch_metrics.record_batch(record_batch.num_rows(), first_batch_after);
+        })
+        .boxed()
+}
+
+/// Fail the stream when no batch arrives within `idle_timeout` of the moment
+/// the next batch is read.
+fn with_idle_timeout(
+    record_batch_stream: RecordBatchStream,
+    idle_timeout: Duration,
+) -> RecordBatchStream {
+    stream::unfold(
+        Some(record_batch_stream),
+        move |record_batch_stream| async move {
+            let mut record_batch_stream = record_batch_stream?;
+            match tokio::time::timeout(idle_timeout, record_batch_stream.next()).await {
+                Ok(record_batch) => Some((record_batch?, Some(record_batch_stream))),
+                Err(_) => Some((
+                    Err(FlightSqlError::Timeout(format!(
+                        "No batch arrived for {:.3}s",
+                        idle_timeout.as_secs_f64()
+                    ))),
+                    None,
+                )),
+            }
+        },
+    )
+    .boxed()
+}
+
+/// Encode record batches as the `FlightData` messages of a DoPut stream.
+///
+/// The first message holds the schema of the batches together with the
+/// `FlightDescriptor`, followed by any dictionaries and the batches themselves.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn flight_data_from_arrow_batches(
+    descriptor: FlightDescriptor,
+    batches: &[RecordBatch],
+) -> ArrowResult<Vec<FlightData>> {
+    let first_batch = batches.first().ok_or_else(|| {
+        ArrowError::InvalidArgumentError("At least one record batch is required".to_string())
+    })?;
 
-    let arrow_schema = arrow::ipc::convert::fb_to_schema(ipc_schema);
+    let options = IpcWriteOptions::default();
+    let data_gen = IpcDataGenerator::default();
+    let mut dictionary_tracker = DictionaryTracker::new(false);
+
+    let schema = data_gen.schema_to_bytes(&first_batch.schema(), &options);
+    let mut flight_data = vec![FlightData {
+        flight_descriptor: Some(descriptor),
+        data_header: schema.ipc_message,
+        ..Default::default()
+    }];
+
+    for batch in batches {
+        let (encoded_dictionaries, encoded_batch) =
+            data_gen.encoded_batch(batch, &mut dictionary_tracker, &options)?;
+        flight_data.extend(
+            encoded_dictionaries
+                .into_iter()
+                .chain(std::iter::once(encoded_batch))
+                .map(|encoded| FlightData {
+                    data_header: encoded.ipc_message,
+                    data_body: encoded.arrow_data,
+                    ..Default::default()
+                }),
+        );
+    }
 
-    Ok(arrow_schema)
+    Ok(flight_data)
 }
 
 pub enum ArrowFlightData {
     RecordBatch(arrow::record_batch::RecordBatch),
     Schema(arrow::datatypes::Schema),
+    /// A dictionary batch with the given id was read by the `FlightDataDecoder`.
+    Dictionary(i64),
 }
 
+/// Decode a single `FlightData` message.
+///
+/// This does not keep any state between messages, so streams carrying
+/// dictionary batches must be read with a `FlightDataDecoder` instead.
 #[tracing::instrument(level = "debug", skip_all)]
 pub fn arrow_data_from_flight_data(
     flight_data: FlightData,
     arrow_schema_ref: &SchemaRef,
-) -> Result<ArrowFlightData> {
-    let ipc_message = arrow::ipc::root_as_message(&flight_data.data_header[..]).map_err(|err| {
-        ArrowError::ParseError(format!("Unable to get root as message: {:?}", err))
-    })?;
+) -> ArrowResult<ArrowFlightData> {
+    FlightDataDecoder::new(arrow_schema_ref.clone()).decode(flight_data)
+}
+
+/// A FlightDataDecoder decodes the `FlightData` messages of a single stream.
+///
+/// Dictionary batches, including delta dictionaries, are collected as they
+/// arrive and resolved when decoding the record batches that follow them.
+#[derive(Debug)]
+pub struct FlightDataDecoder {
+    schema: SchemaRef,
+    dictionaries_by_id: HashMap<i64, ArrayRef>,
+}
 
-    match ipc_message.header_type() {
-        MessageHeader::RecordBatch => {
-            let ipc_record_batch =
-                ipc_message
-                    .header_as_record_batch()
+impl FlightDataDecoder {
+    /// create FlightDataDecoder for a stream with the given schema
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn new(schema: SchemaRef) -> Self {
+        FlightDataDecoder {
+            schema,
+            dictionaries_by_id: HashMap::new(),
+        }
+    }
+
+    /// The schema used to decode the record batches.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Decode the next `FlightData` message of the stream.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn decode(&mut self, flight_data: FlightData) -> ArrowResult<ArrowFlightData> {
+        let ipc_message =
+            arrow::ipc::root_as_message(&flight_data.data_header[..]).map_err(|err| {
+                ArrowError::ParseError(format!("Unable to get root as message: {:?}", err))
+            })?;
+
+        match ipc_message.header_type() {
+            MessageHeader::RecordBatch => {
+                let ipc_record_batch =
+                    ipc_message
+                        .header_as_record_batch()
+                        .ok_or(ArrowError::ComputeError(
+                            "Unable to convert flight data header to a record batch".to_string(),
+                        ))?;
+
+                let body = arrow::buffer::Buffer::from(&flight_data.data_body);
+                let record_batch = read_record_batch(
+                    &body,
+                    ipc_record_batch,
+                    self.schema.clone(),
+                    &self.dictionaries_by_id,
+                    &ipc_message.version(),
+                )?;
+                Ok(ArrowFlightData::RecordBatch(record_batch))
+            }
+            MessageHeader::Schema => {
+                let ipc_schema = ipc_message
+                    .header_as_schema()
                     .ok_or(ArrowError::ComputeError(
-                        "Unable to convert flight data header to a record batch".to_string(),
+                        "Unable to convert flight data header to a schema".to_string(),
                     ))?;
 
-            let dictionaries_by_field = HashMap::new();
-            let record_batch = arrow::ipc::reader::read_record_batch(
-                &arrow::buffer::Buffer::from(&flight_data.data_body),
-                ipc_record_batch,
-                arrow_schema_ref.clone(),
-                &dictionaries_by_field,
-                None,
-                &ipc_message.version(),
-            )?;
-            Ok(ArrowFlightData::RecordBatch(record_batch))
-        }
-        MessageHeader::Schema => {
-            let ipc_schema = ipc_message
-                .header_as_schema()
-                .ok_or(ArrowError::ComputeError(
-                    "Unable to convert flight data header to a schema".to_string(),
-                ))?;
-
-            let arrow_schema = arrow::ipc::convert::fb_to_schema(ipc_schema);
-            Ok(ArrowFlightData::Schema(arrow_schema))
-        }
-        MessageHeader::DictionaryBatch => {
-            let _ = ipc_message
-                .header_as_dictionary_batch()
-                .ok_or(ArrowError::ComputeError(
-                    "Unable to convert flight data header to a dictionary batch".to_string(),
-                ))?;
-            Err(ArrowError::NotYetImplemented(
-                "no idea on how to convert an ipc dictionary batch to an arrow type".to_string(),
-            ))
-        }
-        MessageHeader::Tensor => {
-            let _ = ipc_message
-                .header_as_tensor()
-                .ok_or(ArrowError::ComputeError(
-                    "Unable to convert flight data header to a tensor".to_string(),
-                ))?;
-            Err(ArrowError::NotYetImplemented(
-                "no idea on how to convert an ipc tensor to an arrow type".to_string(),
-            ))
-        }
-        MessageHeader::SparseTensor => {
-            let _ = ipc_message
-                .header_as_sparse_tensor()
-                .ok_or(ArrowError::ComputeError(
-                    "Unable to convert flight data header to a sparse tensor".to_string(),
-                ))?;
-            Err(ArrowError::NotYetImplemented(
-                "no idea on how to convert an ipc sparse tensor to an arrow type".to_string(),
-            ))
+                validate_schema(ipc_schema)?;
+                let arrow_schema = convert::fb_to_schema(ipc_schema);
+                self.schema = SchemaRef::new(arrow_schema.clone());
+                self.dictionaries_by_id.clear();
+                Ok(ArrowFlightData::Schema(arrow_schema))
+            }
+            MessageHeader::DictionaryBatch => {
+                let ipc_dictionary_batch =
+                    ipc_message
+                        .header_as_dictionary_batch()
+                        .ok_or(ArrowError::ComputeError(
+                            "Unable to convert flight data header to a dictionary batch"
+                                .to_string(),
+                        ))?;
+
+                let id = self.read_dictionary(
+                    &arrow::buffer::Buffer::from(&flight_data.data_body),
+                    ipc_dictionary_batch,
+                    &ipc_message.version(),
+                )?;
+                Ok(ArrowFlightData::Dictionary(id))
+            }
+            MessageHeader::Tensor => {
+                let _ = ipc_message
+                    .header_as_tensor()
+                    .ok_or(ArrowError::ComputeError(
+                        "Unable to convert flight data header to a tensor".to_string(),
+                    ))?;
+                Err(ArrowError::NotYetImplemented(
+                    "no idea on how to convert an ipc tensor to an arrow type".to_string(),
+                ))
+            }
+            MessageHeader::SparseTensor => {
+                let _ = ipc_message
+                    .header_as_sparse_tensor()
+                    .ok_or(ArrowError::ComputeError(
+                        "Unable to convert flight data header to a sparse tensor".to_string(),
+                    ))?;
+                Err(ArrowError::NotYetImplemented(
+                    "no idea on how to convert an ipc sparse tensor to an arrow type".to_string(),
+                ))
+            }
+            _ => Err(ArrowError::ComputeError(format!(
+                "Unable to convert message with header_type: '{:?}' to arrow data",
+                ipc_message.header_type()
+            ))),
         }
-        _ => Err(ArrowError::ComputeError(format!(
-            "Unable to convert message with header_type: '{:?}' to arrow data",
-            ipc_message.header_type()
-        ))),
+    }
+
+    /// Read the values of a dictionary batch, appending them to the known
+    /// dictionary with the same id when it is a delta.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn read_dictionary(
+        &mut self,
+        buf: &arrow::buffer::Buffer,
+        batch: DictionaryBatch,
+        metadata: &MetadataVersion,
+    ) -> ArrowResult<i64> {
+        let id = batch.id();
+        let value_type = self
+            .schema
+            .fields_with_dict_id(id)
+            .first()
+            .and_then(|field| match field.data_type() {
+                DataType::Dictionary(_, value_type) => Some(value_type.as_ref().clone()),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                ArrowError::ParseError(format!("Dictionary id {} not found in schema", id))
+            })?;
+
+        let data = batch.data().ok_or_else(|| {
+            ArrowError::ParseError(format!("Dictionary batch {} contains no data", id))
+        })?;
+
+        // The dictionary batch only holds the values, read as a single column batch.
+        let values =
+            read_dictionary_values(buf, data, &value_type, &self.dictionaries_by_id, metadata)?;
+
+        let values = match self.dictionaries_by_id.get(&id) {
+            Some(existing) if batch.isDelta() => {
+                arrow::compute::concat(&[existing.as_ref(), values.as_ref()])?
+            }
+            _ => values,
+        };
+        self.dictionaries_by_id.insert(id, values);
+
+        Ok(id)
     }
 }
 
@@ -528,6 +1309,7 @@ prost_message_ext!(
     CommandGetSqlInfo,
     CommandGetTableTypes,
     CommandGetTables,
+    CommandGetXdbcTypeInfo,
     CommandPreparedStatementQuery,
     CommandPreparedStatementUpdate,
     CommandStatementQuery,
@@ -568,9 +1350,7 @@ impl ProstAnyExt for prost_types::Any {
         if !self.is::<M>() {
             return Ok(None);
         }
-        let m = prost::Message::decode(&*self.value).map_err(|err| {
-            ArrowError::ParseError(format!("Unable to decode Any value: {}", err))
-        })?;
+        let m = prost::Message::decode(&*self.value)?;
         Ok(Some(m))
     }
 
@@ -598,19 +1378,8 @@ impl TryFrom<IpcMessage> for Schema {
     type Error = ArrowError;
 
     #[tracing::instrument(level = "debug", skip_all)]
-    fn try_from(value: IpcMessage) -> Result<Self> {
-        // CONTINUATION TAKES 4 BYTES
-        // SIZE TAKES 4 BYTES (so read msg as size prefixed)
-        let msg = size_prefixed_root_as_message(&value.0[4..]).map_err(|err| {
-            ArrowError::ParseError(format!(
-                "Unable to convert flight info to a message: {}",
-                err
-            ))
-        })?;
-        let ipc_schema = msg.header_as_schema().ok_or_else(|| {
-            ArrowError::ParseError("Unable to convert flight info to a schema".to_string())
-        })?;
-        Ok(convert::fb_to_schema(ipc_schema))
+    fn try_from(value: IpcMessage) -> ArrowResult<Self> {
+        decode_ipc_schema(&value.0)
     }
 }
 
@@ -0,0 +1,941 @@
+use std::{
+    collections::HashSet,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate as RustlsCertificate, PrivateKey, ServerName,
+};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Uri};
+use tower::service_fn;
+
+use crate::{
+    client::FlightSqlServiceClient,
+    error::{FlightSqlError, Result},
+    interceptor::HeaderInterceptor,
+    retry::RetryPolicy,
+};
+
+/// The TLS settings used to connect to a Flight SQL server.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// PEM encoded certificates of the authorities that signed the server
+    /// certificate. The system roots are used when not set.
+    pub ca_certificate: Option<Vec<u8>>,
+    /// PEM encoded certificate and private key presented to the server for
+    /// mutual TLS.
+    pub client_identity: Option<(Vec<u8>, Vec<u8>)>,
+    /// The name used for SNI and to verify the server certificate, instead of
+    /// the hostname.
+    pub domain_name: Option<String>,
+    /// Accept any certificate the server presents. Only meant for development
+    /// clusters with self-signed certificates, and not allowed together with
+    /// `ca_certificate`.
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsOptions {
+    /// Read the PEM encoded CA bundle and client certificate and key from files.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_pem_files(
+        ca_certificate: Option<&Path>,
+        client_certificate: Option<&Path>,
+        client_key: Option<&Path>,
+    ) -> Result<Self> {
+        let client_identity = match (client_certificate, client_key) {
+            (Some(certificate), Some(key)) => {
+                Some((std::fs::read(certificate)?, std::fs::read(key)?))
+            }
+            (None, None) => None,
+            _ => {
+                return Err(FlightSqlError::InvalidArgument(
+                    "A client certificate and key must be provided together".to_string(),
+                ))
+            }
+        };
+        Ok(TlsOptions {
+            ca_certificate: ca_certificate.map(std::fs::read).transpose()?,
+            client_identity,
+            ..Default::default()
+        })
+    }
+}
+
+/// The address a Flight SQL server listens on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerAddress {
+    /// A hostname or IP address, without brackets for IPv6, and a port.
+    Tcp { host: String, port: u16 },
+    /// The path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerAddress::Tcp { host, port } if host.contains(':') => {
+                write!(f, "[{}]:{}", host, port)
+            }
+            ServerAddress::Tcp { host, port } => write!(f, "{}:{}", host, port),
+            ServerAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// A ClientBuilder configures and opens the connection used by a
+/// FlightSqlServiceClient.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    address: ServerAddress,
+    tls: Option<TlsOptions>,
+    credentials: Option<(String, String)>,
+    token: Option<String>,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    http2_keepalive_interval: Option<Duration>,
+    http2_keepalive_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    retry_policy: RetryPolicy,
+}
+
+impl ClientBuilder {
+    /// create ClientBuilder for a server listening on the given host and port
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn new(hostname: impl Into<String>, port: u16) -> Self {
+        let host = hostname.into();
+        let host = match host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+            Some(host) => host.to_string(),
+            None => host,
+        };
+        Self::with_address(ServerAddress::Tcp { host, port })
+    }
+
+    /// create ClientBuilder for a server listening on the given Unix domain socket
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn new_unix(path: impl Into<PathBuf>) -> Self {
+        Self::with_address(ServerAddress::Unix(path.into()))
+    }
+
+    fn with_address(address: ServerAddress) -> Self {
+        ClientBuilder {
+            address,
+            tls: None,
+            credentials: None,
+            token: None,
+            headers: vec![],
+            timeout: None,
+            connect_timeout: None,
+            http2_keepalive_interval: None,
+            http2_keepalive_timeout: None,
+            tcp_keepalive: None,
+            retry_policy: RetryPolicy::none(),
+        }
+    }
+
+    /// create ClientBuilder from a uri, such as the uri of a Location.
+    ///
+    /// The schemes `grpc`, `grpc+tcp` and `http` connect without TLS,
+    /// `grpc+tls` and `https` with TLS and `grpc+unix` to the Unix domain
+    /// socket at the path of the uri. IPv6 addresses are enclosed in brackets,
+    /// as in `grpc+tcp://[::1]:50051`.
+    ///
+    /// These query parameters are supported:
+    /// * `timeout` and `connect_timeout` - in seconds, see `with_timeout` and
+    ///   `with_connect_timeout`.
+    /// * `http2_keepalive_interval`, `http2_keepalive_timeout` and
+    ///   `tcp_keepalive` - in seconds, see `with_http2_keepalive` and
+    ///   `with_tcp_keepalive`.
+    /// * `max_attempts` - the number of times the calls that are safe to
+    ///   repeat are made at most, see `with_retry_policy`.
+    /// * `token` - the bearer token to send with every request.
+    /// * `header.<key>` - a header to send with every request.
+    /// * `tls_domain_name` and `tls_insecure_skip_verify` - see TlsOptions,
+    ///   only for uris with TLS.
+    ///
+    /// Each parameter can only be given once.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            FlightSqlError::InvalidArgument(format!("Invalid uri {}: {}", uri, reason))
+        };
+        let (scheme, rest) = uri
+            .split_once("://")
+            .ok_or_else(|| invalid("missing scheme"))?;
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, query),
+            None => (rest, ""),
+        };
+        let mut builder = match scheme.to_ascii_lowercase().as_str() {
+            "grpc" | "grpc+tcp" | "http" => {
+                let (host, port) = parse_authority(rest, 80).map_err(|e| invalid(&e))?;
+                ClientBuilder::new(host, port)
+            }
+            "grpc+tls" | "https" => {
+                let (host, port) = parse_authority(rest, 443).map_err(|e| invalid(&e))?;
+                ClientBuilder::new(host, port).with_tls(TlsOptions::default())
+            }
+            "grpc+unix" | "unix" => {
+                if rest.is_empty() {
+                    return Err(invalid("missing socket path"));
+                }
+                ClientBuilder::new_unix(percent_decode(rest).map_err(|e| invalid(&e))?)
+            }
+            _ => {
+                return Err(FlightSqlError::InvalidArgument(format!(
+                    "Unsupported uri scheme: {}",
+                    scheme
+                )))
+            }
+        };
+
+        let mut keys = HashSet::new();
+        for parameter in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let key = percent_decode(key).map_err(|e| invalid(&e))?;
+            let value = percent_decode(value).map_err(|e| invalid(&e))?;
+            if !keys.insert(key.clone()) {
+                return Err(invalid(&format!("duplicate parameter {}", key)));
+            }
+            match key.as_str() {
+                "timeout" => {
+                    builder = builder.with_timeout(parse_seconds(&value).map_err(|e| invalid(&e))?)
+                }
+                "connect_timeout" => {
+                    builder = builder
+                        .with_connect_timeout(parse_seconds(&value).map_err(|e| invalid(&e))?)
+                }
+                "http2_keepalive_interval" => {
+                    builder.http2_keepalive_interval =
+                        Some(parse_seconds(&value).map_err(|e| invalid(&e))?)
+                }
+                "http2_keepalive_timeout" => {
+                    builder.http2_keepalive_timeout =
+                        Some(parse_seconds(&value).map_err(|e| invalid(&e))?)
+                }
+                "tcp_keepalive" => {
+                    builder =
+                        builder.with_tcp_keepalive(parse_seconds(&value).map_err(|e| invalid(&e))?)
+                }
+                "max_attempts" => {
+                    let max_attempts = match value.parse() {
+                        Ok(attempts) if attempts > 0 => attempts,
+                        _ => return Err(invalid(&format!("invalid max_attempts {}", value))),
+                    };
+                    builder = builder.with_retry_policy(RetryPolicy {
+                        max_attempts,
+                        ..Default::default()
+                    });
+                }
+                "token" => builder = builder.with_token(value),
+                "tls_domain_name" | "tls_insecure_skip_verify" => {
+                    let tls = builder
+                        .tls
+                        .as_mut()
+                        .ok_or_else(|| invalid(&format!("{} requires TLS", key)))?;
+                    if key == "tls_domain_name" {
+                        tls.domain_name = Some(value);
+                    } else {
+                        tls.insecure_skip_verify = parse_bool(&value).map_err(|e| invalid(&e))?;
+                    }
+                }
+                _ => match key.strip_prefix("header.") {
+                    Some(header) if !header.is_empty() => {
+                        builder = builder.with_header(header, value)
+                    }
+                    _ => return Err(invalid(&format!("unknown parameter {}", key))),
+                },
+            }
+        }
+        Ok(builder)
+    }
+
+    /// create ClientBuilder for the location of an endpoint, such as
+    /// `grpc+tls://node-2.example.com:443`.
+    ///
+    /// Only the address and whether to use TLS are taken from the location.
+    /// The other settings are those of this builder, including its TLS
+    /// options, except for the ones set by query parameters of the location
+    /// uri.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn for_location(&self, uri: &str) -> Result<Self> {
+        let location = ClientBuilder::from_uri(uri)?;
+        let mut builder = self.clone();
+        builder.tls = location.tls.map(|location_tls| {
+            let mut tls = self.tls.clone().unwrap_or_default();
+            if location_tls.domain_name.is_some() {
+                tls.domain_name = location_tls.domain_name;
+            }
+            tls.insecure_skip_verify |= location_tls.insecure_skip_verify;
+            tls
+        });
+        builder.address = location.address;
+        builder.token = location.token.or(builder.token);
+        builder.headers.extend(location.headers);
+        builder.timeout = location.timeout.or(builder.timeout);
+        builder.connect_timeout = location.connect_timeout.or(builder.connect_timeout);
+        builder.http2_keepalive_interval = location
+            .http2_keepalive_interval
+            .or(builder.http2_keepalive_interval);
+        builder.http2_keepalive_timeout = location
+            .http2_keepalive_timeout
+            .or(builder.http2_keepalive_timeout);
+        builder.tcp_keepalive = location.tcp_keepalive.or(builder.tcp_keepalive);
+        if location.retry_policy != RetryPolicy::none() {
+            builder.retry_policy = location.retry_policy;
+        }
+        Ok(builder)
+    }
+
+    /// The headers sent to a location with the uri of this builder: those
+    /// of the client, with the deadline, headers and token set by the query
+    /// parameters of the uri, if any, added to a copy of them.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn location_headers(&self, headers: HeaderInterceptor) -> Result<HeaderInterceptor> {
+        if self.timeout.is_none() && self.headers.is_empty() && self.token.is_none() {
+            return Ok(headers);
+        }
+        let headers = headers.detach();
+        if self.timeout.is_some() {
+            headers.set_timeout(self.timeout);
+        }
+        for (key, value) in &self.headers {
+            headers.insert(key, value)?;
+        }
+        if let Some(token) = &self.token {
+            headers.set_token(token)?;
+        }
+        Ok(headers)
+    }
+
+    /// The address of the server.
+    pub fn address(&self) -> &ServerAddress {
+        &self.address
+    }
+
+    /// The TLS settings, when the connection uses TLS.
+    pub fn tls(&self) -> Option<&TlsOptions> {
+        self.tls.as_ref()
+    }
+
+    /// Connect with TLS, using the given settings.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Authenticate with a username and password using the Handshake RPC once
+    /// connected.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Send the given bearer token with every request.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Send the given header with every request.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+
+    /// Fail calls that get no response within the timeout. The timeout is
+    /// also sent to the server as the `grpc-timeout` deadline of each call,
+    /// except DoGet and DoPut, whose streams may take longer and are better
+    /// bounded with `DoGetOptions::idle_timeout`.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Fail when the connection can not be opened within the timeout.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Send an HTTP/2 ping every `interval`, also while no call is running,
+    /// and close the connection when the server does not answer within
+    /// `timeout`, or the default timeout of hyper when not set.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_http2_keepalive(mut self, interval: Duration, timeout: Option<Duration>) -> Self {
+        self.http2_keepalive_interval = Some(interval);
+        self.http2_keepalive_timeout = timeout;
+        self
+    }
+
+    /// Send TCP keepalive probes once the connection was idle for the given
+    /// time. Not applied with `insecure_skip_verify`, whose connector opens
+    /// the TCP connection itself.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_tcp_keepalive(mut self, idle: Duration) -> Self {
+        self.tcp_keepalive = Some(idle);
+        self
+    }
+
+    /// Retry the calls that are safe to repeat with the policy when the
+    /// server is unavailable, see `FlightSqlServiceClient::with_retry_policy`.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Open the connection and create a FlightSqlServiceClient using it.
+    ///
+    /// When credentials were given, the client authenticates before it is
+    /// returned, and the token returned by the server replaces the one set
+    /// with `with_token`.
+    #[tracing::instrument(skip_all)]
+    pub async fn connect(&self) -> Result<FlightSqlServiceClient> {
+        // The credentials are not needed to connect to the locations of
+        // endpoints, which use the token obtained here.
+        let builder = ClientBuilder {
+            credentials: None,
+            ..self.clone()
+        };
+        let client = FlightSqlServiceClient::new(self.connect_channel().await?)
+            .with_retry_policy(self.retry_policy.clone())
+            .with_builder(builder);
+        client.set_timeout(self.timeout);
+        for (key, value) in &self.headers {
+            client.set_header(key, value)?;
+        }
+        if let Some(token) = &self.token {
+            client.set_token(token)?;
+        }
+        if let Some((username, password)) = &self.credentials {
+            client.handshake(username, password).await?;
+        }
+        Ok(client)
+    }
+
+    /// Open the connection to the server.
+    #[tracing::instrument(skip_all, fields(address = %self.address))]
+    pub async fn connect_channel(&self) -> Result<Channel> {
+        // The insecure connector does the TLS handshake itself, so tonic must
+        // not add its own TLS layer for an https uri.
+        let scheme = match &self.tls {
+            Some(tls) if !tls.insecure_skip_verify => "https",
+            _ => "http",
+        };
+        // The authority of the uri is not used to connect to a Unix domain
+        // socket, but it is sent as the :authority of the requests.
+        let authority = match &self.address {
+            ServerAddress::Tcp { .. } => self.address.to_string(),
+            ServerAddress::Unix(_) => "localhost".to_string(),
+        };
+        let mut endpoint = Endpoint::from_shared(format!("{}://{}", scheme, authority))
+            .map_err(FlightSqlError::from)?;
+        if let Some(timeout) = self.timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+        if let Some(interval) = self.http2_keepalive_interval {
+            endpoint = endpoint
+                .http2_keep_alive_interval(interval)
+                .keep_alive_while_idle(true);
+        }
+        if let Some(timeout) = self.http2_keepalive_timeout {
+            endpoint = endpoint.keep_alive_timeout(timeout);
+        }
+        endpoint = endpoint.tcp_keepalive(self.tcp_keepalive);
+
+        if let ServerAddress::Unix(path) = &self.address {
+            if self.tls.is_some() {
+                return Err(FlightSqlError::InvalidArgument(
+                    "TLS is not supported over Unix domain sockets".to_string(),
+                ));
+            }
+            return connect_unix(endpoint, path).await;
+        }
+        match &self.tls {
+            None => endpoint.connect().await,
+            Some(tls) if tls.insecure_skip_verify && tls.ca_certificate.is_some() => {
+                return Err(FlightSqlError::InvalidArgument(
+                    "A CA certificate can not be used when the certificate of the server is not verified"
+                        .to_string(),
+                ))
+            }
+            Some(tls) if tls.insecure_skip_verify => return connect_insecure(endpoint, tls).await,
+            Some(tls) => {
+                endpoint
+                    .tls_config(client_tls_config(tls))
+                    .map_err(FlightSqlError::from)?
+                    .connect()
+                    .await
+            }
+        }
+        .map_err(FlightSqlError::from)
+    }
+}
+
+/// Split the authority of a uri into its host, without the brackets of an
+/// IPv6 address, and port.
+fn parse_authority(
+    authority: &str,
+    default_port: u16,
+) -> std::result::Result<(String, u16), String> {
+    let authority = authority.strip_suffix('/').unwrap_or(authority);
+    if authority.contains('/') || authority.contains('@') {
+        return Err("only a host and port are supported".to_string());
+    }
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| "missing ] after the IPv6 address".to_string())?;
+            match rest {
+                "" => (host, None),
+                _ => (
+                    host,
+                    Some(
+                        rest.strip_prefix(':')
+                            .ok_or_else(|| "expected a port after the IPv6 address".to_string())?,
+                    ),
+                ),
+            }
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    if host.is_empty() {
+        return Err("missing host".to_string());
+    }
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| format!("invalid port {}", port))?,
+        None => default_port,
+    };
+    Ok((host.to_string(), port))
+}
+
+/// Decode the %XX escapes of a part of a uri.
+fn percent_decode(value: &str) -> std::result::Result<String, String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid escape in {}", value))?;
+            bytes.push(hex);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("invalid UTF-8 in {}", value))
+}
+
+/// Parse a number of seconds, which may have a fraction, as in the timeouts of
+/// a uri.
+pub fn parse_seconds(value: &str) -> std::result::Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("invalid number of seconds {}", value))
+}
+
+fn parse_bool(value: &str) -> std::result::Result<bool, String> {
+    match value {
+        "" | "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(format!("invalid boolean {}", value)),
+    }
+}
+
+/// Connect to a Unix domain socket instead of the uri of the endpoint.
+#[cfg(unix)]
+#[tracing::instrument(level = "debug", skip_all)]
+async fn connect_unix(endpoint: Endpoint, path: &Path) -> Result<Channel> {
+    let path = path.to_path_buf();
+    endpoint
+        .connect_with_connector(service_fn(move |_: Uri| {
+            tokio::net::UnixStream::connect(path.clone())
+        }))
+        .await
+        .map_err(FlightSqlError::from)
+}
+
+#[cfg(not(unix))]
+#[tracing::instrument(level = "debug", skip_all)]
+async fn connect_unix(_endpoint: Endpoint, _path: &Path) -> Result<Channel> {
+    Err(FlightSqlError::InvalidArgument(
+        "Unix domain sockets are not supported on this platform".to_string(),
+    ))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn client_tls_config(tls: &TlsOptions) -> ClientTlsConfig {
+    let mut config = ClientTlsConfig::new();
+    if let Some(ca_certificate) = &tls.ca_certificate {
+        config = config.ca_certificate(Certificate::from_pem(ca_certificate));
+    }
+    if let Some((certificate, key)) = &tls.client_identity {
+        config = config.identity(Identity::from_pem(certificate, key));
+    }
+    if let Some(domain_name) = &tls.domain_name {
+        config = config.domain_name(domain_name);
+    }
+    config
+}
+
+/// Connect with a connector that does the TLS handshake itself, without
+/// verifying the certificate of the server.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn connect_insecure(endpoint: Endpoint, tls: &TlsOptions) -> Result<Channel> {
+    tracing::warn!("The certificate of the server is not verified");
+
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(NoCertificateVerification));
+    let mut config = match &tls.client_identity {
+        Some((certificate, key)) => config
+            .with_single_cert(parse_pem_certificates(certificate)?, parse_pem_key(key)?)
+            .map_err(|e| FlightSqlError::InvalidArgument(e.to_string()))?,
+        None => config.with_no_client_auth(),
+    };
+    config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let connector = TlsConnector::from(Arc::new(config));
+    let domain_name = tls.domain_name.clone();
+    endpoint
+        .connect_with_connector(service_fn(move |uri: Uri| {
+            let connector = connector.clone();
+            let domain_name = domain_name.clone();
+            async move {
+                let host = uri
+                    .host()
+                    .unwrap_or_default()
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_string();
+                let server_name = ServerName::try_from(domain_name.as_deref().unwrap_or(&host))
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let stream =
+                    TcpStream::connect((host.as_str(), uri.port_u16().unwrap_or(443))).await?;
+                connector.connect(server_name, stream).await
+            }
+        }))
+        .await
+        .map_err(FlightSqlError::from)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn parse_pem_certificates(pem: &[u8]) -> Result<Vec<RustlsCertificate>> {
+    let certificates = rustls_pemfile::certs(&mut &*pem)?;
+    if certificates.is_empty() {
+        return Err(FlightSqlError::InvalidArgument(
+            "No certificate found in PEM data".to_string(),
+        ));
+    }
+    Ok(certificates.into_iter().map(RustlsCertificate).collect())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn parse_pem_key(pem: &[u8]) -> Result<PrivateKey> {
+    rustls_pemfile::read_all(&mut &*pem)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            FlightSqlError::InvalidArgument("No private key found in PEM data".to_string())
+        })
+}
+
+/// A ServerCertVerifier that accepts every certificate.
+struct NoCertificateVerification;
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &RustlsCertificate,
+        _intermediates: &[RustlsCertificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_keeps_the_settings_of_the_builder() {
+        let builder = ClientBuilder::new("flight.example.com", 443)
+            .with_tls(TlsOptions {
+                ca_certificate: Some(b"ca".to_vec()),
+                client_identity: Some((b"certificate".to_vec(), b"key".to_vec())),
+                domain_name: Some("flight.internal".to_string()),
+                insecure_skip_verify: false,
+            })
+            .with_token("token")
+            .with_header("x-tenant", "acme")
+            .with_timeout(Duration::from_secs(30))
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_http2_keepalive(Duration::from_secs(10), Some(Duration::from_secs(2)))
+            .with_tcp_keepalive(Duration::from_secs(60))
+            .with_retry_policy(RetryPolicy::default());
+
+        let location = builder
+            .for_location("grpc+tls://node-2.example.com:8443")
+            .unwrap();
+        assert_eq!(
+            location.address,
+            ServerAddress::Tcp {
+                host: "node-2.example.com".to_string(),
+                port: 8443
+            }
+        );
+        let tls = location.tls.unwrap();
+        assert_eq!(tls.ca_certificate, Some(b"ca".to_vec()));
+        assert!(tls.client_identity.is_some());
+        assert_eq!(tls.domain_name.as_deref(), Some("flight.internal"));
+        assert_eq!(location.token.as_deref(), Some("token"));
+        assert_eq!(
+            location.headers,
+            vec![("x-tenant".to_string(), "acme".to_string())]
+        );
+        assert_eq!(location.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(location.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(
+            location.http2_keepalive_interval,
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            location.http2_keepalive_timeout,
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(location.tcp_keepalive, Some(Duration::from_secs(60)));
+        assert_eq!(location.retry_policy, RetryPolicy::default());
+    }
+
+    #[test]
+    fn location_parameters_take_precedence() {
+        let builder = ClientBuilder::from_uri(
+            "grpc+tls://flight.example.com?timeout=30&token=main&header.x-tenant=acme",
+        )
+        .unwrap();
+
+        let location = builder
+            .for_location("grpc+tcp://[::1]:50051?timeout=5&header.x-node=2&max_attempts=4")
+            .unwrap();
+        assert!(location.tls.is_none());
+        assert_eq!(location.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(location.token.as_deref(), Some("main"));
+        assert_eq!(
+            location.headers,
+            vec![
+                ("x-tenant".to_string(), "acme".to_string()),
+                ("x-node".to_string(), "2".to_string())
+            ]
+        );
+        assert_eq!(location.retry_policy.max_attempts, 4);
+
+        let location = builder
+            .for_location("grpc+tls://node-2.example.com?tls_domain_name=node-2.internal")
+            .unwrap();
+        assert_eq!(
+            location.tls.unwrap().domain_name.as_deref(),
+            Some("node-2.internal")
+        );
+    }
+
+    fn tcp(host: &str, port: u16) -> ServerAddress {
+        ServerAddress::Tcp {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    #[test]
+    fn uri_defaults_to_the_port_of_the_scheme() {
+        for (uri, port, tls) in [
+            ("grpc://flight.example.com", 80, false),
+            ("grpc+tcp://flight.example.com/", 80, false),
+            ("http://flight.example.com", 80, false),
+            ("grpc+tls://flight.example.com", 443, true),
+            ("HTTPS://flight.example.com", 443, true),
+        ] {
+            let builder = ClientBuilder::from_uri(uri).unwrap();
+            assert_eq!(builder.address, tcp("flight.example.com", port), "{}", uri);
+            assert_eq!(builder.tls.is_some(), tls, "{}", uri);
+        }
+        let builder = ClientBuilder::from_uri("grpc+tls://flight.example.com:8443").unwrap();
+        assert_eq!(builder.address, tcp("flight.example.com", 8443));
+    }
+
+    #[test]
+    fn uri_with_ipv6_address() {
+        let builder = ClientBuilder::from_uri("grpc+tcp://[::1]:50051").unwrap();
+        assert_eq!(builder.address, tcp("::1", 50051));
+        let builder = ClientBuilder::from_uri("grpc+tls://[2001:db8::1]").unwrap();
+        assert_eq!(builder.address, tcp("2001:db8::1", 443));
+        for uri in [
+            "grpc+tcp://[::1:50051",
+            "grpc+tcp://[::1]50051",
+            "grpc+tcp://::1",
+        ] {
+            assert!(ClientBuilder::from_uri(uri).is_err(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn uri_with_unix_socket() {
+        let builder = ClientBuilder::from_uri("grpc+unix:///run/flight.sock").unwrap();
+        assert_eq!(
+            builder.address,
+            ServerAddress::Unix(PathBuf::from("/run/flight.sock"))
+        );
+        let builder = ClientBuilder::from_uri("unix:///tmp/flight%20sql.sock?timeout=1").unwrap();
+        assert_eq!(
+            builder.address,
+            ServerAddress::Unix(PathBuf::from("/tmp/flight sql.sock"))
+        );
+        assert_eq!(builder.timeout, Some(Duration::from_secs(1)));
+        assert!(ClientBuilder::from_uri("grpc+unix://").is_err());
+    }
+
+    #[test]
+    fn uri_parameters() {
+        let builder = ClientBuilder::from_uri(
+            "grpc+tls://flight.example.com?timeout=1.5&connect_timeout=2\
+             &http2_keepalive_interval=3&http2_keepalive_timeout=4&tcp_keepalive=5\
+             &max_attempts=3&token=a%2Bb&header.x-tenant=acme%20corp&header.x-node=2\
+             &tls_domain_name=flight.internal&tls_insecure_skip_verify",
+        )
+        .unwrap();
+        assert_eq!(builder.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(builder.connect_timeout, Some(Duration::from_secs(2)));
+        assert_eq!(
+            builder.http2_keepalive_interval,
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            builder.http2_keepalive_timeout,
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(builder.tcp_keepalive, Some(Duration::from_secs(5)));
+        assert_eq!(builder.retry_policy.max_attempts, 3);
+        assert_eq!(builder.token.as_deref(), Some("a+b"));
+        assert_eq!(
+            builder.headers,
+            vec![
+                ("x-tenant".to_string(), "acme corp".to_string()),
+                ("x-node".to_string(), "2".to_string())
+            ]
+        );
+        let tls = builder.tls.unwrap();
+        assert_eq!(tls.domain_name.as_deref(), Some("flight.internal"));
+        assert!(tls.insecure_skip_verify);
+    }
+
+    #[test]
+    fn invalid_uris() {
+        for uri in [
+            "flight.example.com:443",
+            "ftp://flight.example.com",
+            "grpc+tcp://",
+            "grpc+tcp://:443",
+            "grpc+tcp://flight.example.com:port",
+            "grpc+tcp://flight.example.com:65536",
+            "grpc+tcp://user@flight.example.com",
+            "grpc+tcp://flight.example.com/path",
+            "grpc+tcp://flight.example.com?unknown=1",
+            "grpc+tcp://flight.example.com?timeout=1&timeout=2",
+            "grpc+tcp://flight.example.com?header.x-node=1&header.x-node=2",
+            "grpc+tcp://flight.example.com?header.=1",
+            "grpc+tcp://flight.example.com?timeout=-1",
+            "grpc+tcp://flight.example.com?max_attempts=0",
+            "grpc+tcp://flight.example.com?tls_domain_name=flight.internal",
+            "grpc+tls://flight.example.com?tls_insecure_skip_verify=maybe",
+            "grpc+tcp://flight.example.com?token=%zz",
+        ] {
+            assert!(
+                matches!(
+                    ClientBuilder::from_uri(uri),
+                    Err(FlightSqlError::InvalidArgument(_))
+                ),
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn authority() {
+        assert_eq!(
+            parse_authority("flight.example.com", 80),
+            Ok(("flight.example.com".to_string(), 80))
+        );
+        assert_eq!(
+            parse_authority("flight.example.com:8080/", 80),
+            Ok(("flight.example.com".to_string(), 8080))
+        );
+        assert_eq!(
+            parse_authority("[::1]:8080", 80),
+            Ok(("::1".to_string(), 8080))
+        );
+        assert_eq!(parse_authority("[::1]", 443), Ok(("::1".to_string(), 443)));
+        for authority in ["", ":80", "[]:80", "[::1", "host:", "host:-1", "a/b", "u@h"] {
+            assert!(parse_authority(authority, 80).is_err(), "{}", authority);
+        }
+    }
+
+    #[test]
+    fn percent_escapes() {
+        assert_eq!(percent_decode("plain"), Ok("plain".to_string()));
+        assert_eq!(percent_decode("a%20b%2fc"), Ok("a b/c".to_string()));
+        assert_eq!(percent_decode("%C3%A9"), Ok("é".to_string()));
+        for value in ["%", "100%", "%2", "%zz", "%+1", "%C3"] {
+            assert!(percent_decode(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn seconds() {
+        assert_eq!(parse_seconds("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_seconds("2.5"), Ok(Duration::from_millis(2500)));
+        for value in ["", "-1", "NaN", "inf", "1s"] {
+            assert!(parse_seconds(value).is_err(), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::{error::Error, fmt, io};
+
+use arrow::error::ArrowError;
+use tonic::{Code, Status};
+
+/// The errors returned by the Flight SQL client.
+///
+/// They convert to an `ArrowError` for compatibility. Every variant but
+/// `Arrow` becomes an `ArrowError::ExternalError`, from which the original
+/// error can be recovered with `FlightSqlError::downcast_ref`.
+#[derive(Debug)]
+pub enum FlightSqlError {
+    /// The server answered a call with an error status, which holds the
+    /// code, message, binary details and metadata returned by the server.
+    Status(Box<Status>),
+    /// The connection to the server could not be opened or was lost.
+    Transport(tonic::transport::Error),
+    /// A protobuf or Arrow IPC message returned by the server could not be
+    /// decoded.
+    Decode(Box<dyn Error + Send + Sync>),
+    /// The server returned a response that does not follow the protocol.
+    Protocol(String),
+    /// Arrow data, such as IPC messages, could not be encoded or decoded.
+    Arrow(ArrowError),
+    /// The arguments given by the caller are invalid.
+    InvalidArgument(String),
+    /// A local file, such as a certificate, could not be read.
+    Io(io::Error),
+    /// The server sent nothing for longer than the configured timeout.
+    Timeout(String),
+}
+
+/// The result of the operations of the Flight SQL client.
+pub type Result<T> = std::result::Result<T, FlightSqlError>;
+
+impl FlightSqlError {
+    /// The gRPC status code returned by the server, if the server returned one.
+    pub fn code(&self) -> Option<Code> {
+        match self {
+            FlightSqlError::Status(status) => Some(status.code()),
+            _ => None,
+        }
+    }
+
+    /// The FlightSqlError an ArrowError was converted from, if any.
+    pub fn downcast_ref(err: &ArrowError) -> Option<&FlightSqlError> {
+        match err {
+            ArrowError::ExternalError(err) => err.downcast_ref::<FlightSqlError>(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FlightSqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlightSqlError::Status(status) => {
+                write!(
+                    f,
+                    "Server returned {:?}: {}",
+                    status.code(),
+                    status.message()
+                )
+            }
+            FlightSqlError::Transport(err) => match err.source() {
+                Some(source) => write!(f, "Transport error: {}: {}", err, source),
+                None => write!(f, "Transport error: {}", err),
+            },
+            FlightSqlError::Decode(err) => write!(f, "Invalid message: {}", err),
+            FlightSqlError::Protocol(message) => write!(f, "Protocol error: {}", message),
+            FlightSqlError::Arrow(err) => write!(f, "{}", err),
+            FlightSqlError::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+            FlightSqlError::Io(err) => write!(f, "I/O error: {}", err),
+            FlightSqlError::Timeout(message) => write!(f, "Timeout: {}", message),
+        }
+    }
+}
+
+impl Error for FlightSqlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FlightSqlError::Status(status) => Some(status.as_ref()),
+            FlightSqlError::Transport(err) => Some(err),
+            FlightSqlError::Decode(err) => Some(err.as_ref()),
+            FlightSqlError::Arrow(err) => Some(err),
+            FlightSqlError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Status> for FlightSqlError {
+    fn from(status: Status) -> Self {
+        FlightSqlError::Status(Box::new(status))
+    }
+}
+
+impl From<tonic::transport::Error> for FlightSqlError {
+    fn from(err: tonic::transport::Error) -> Self {
+        FlightSqlError::Transport(err)
+    }
+}
+
+impl From<prost::DecodeError> for FlightSqlError {
+    fn from(err: prost::DecodeError) -> Self {
+        FlightSqlError::Decode(Box::new(err))
+    }
+}
+
+impl From<io::Error> for FlightSqlError {
+    fn from(err: io::Error) -> Self {
+        FlightSqlError::Io(err)
+    }
+}
+
+impl From<ArrowError> for FlightSqlError {
+    /// Wrap the ArrowError, or unwrap the FlightSqlError it was converted from.
+    fn from(err: ArrowError) -> Self {
+        match err {
+            ArrowError::ExternalError(err) => match err.downcast::<FlightSqlError>() {
+                Ok(err) => *err,
+                Err(err) => FlightSqlError::Arrow(ArrowError::ExternalError(err)),
+            },
+            err => FlightSqlError::Arrow(err),
+        }
+    }
+}
+
+impl From<FlightSqlError> for ArrowError {
+    fn from(err: FlightSqlError) -> Self {
+        match err {
+            FlightSqlError::Arrow(err) => err,
+            err => ArrowError::ExternalError(Box::new(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors() -> Vec<FlightSqlError> {
+        vec![
+            Status::unavailable("down").into(),
+            FlightSqlError::Decode("bad message".into()),
+            FlightSqlError::Protocol("no token".to_string()),
+            FlightSqlError::InvalidArgument("bad uri".to_string()),
+            io::Error::new(io::ErrorKind::NotFound, "no certificate").into(),
+            FlightSqlError::Timeout("idle".to_string()),
+        ]
+    }
+
+    #[test]
+    fn status_code_survives_the_conversions() {
+        let err = FlightSqlError::from(Status::permission_denied("denied"));
+        assert_eq!(err.code(), Some(Code::PermissionDenied));
+
+        let err = ArrowError::from(err);
+        assert!(matches!(err, ArrowError::ExternalError(_)));
+        let recovered = FlightSqlError::downcast_ref(&err).unwrap();
+        assert_eq!(recovered.code(), Some(Code::PermissionDenied));
+
+        let err = FlightSqlError::from(err);
+        assert_eq!(err.code(), Some(Code::PermissionDenied));
+        assert_eq!(err.to_string(), "Server returned PermissionDenied: denied");
+    }
+
+    #[test]
+    fn variants_survive_the_conversions() {
+        for err in errors() {
+            let message = err.to_string();
+            let variant = std::mem::discriminant(&err);
+            let err = ArrowError::from(err);
+            assert!(matches!(err, ArrowError::ExternalError(_)), "{}", message);
+            let err = FlightSqlError::from(err);
+            assert_eq!(std::mem::discriminant(&err), variant, "{}", message);
+            assert_eq!(err.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn only_status_has_a_code() {
+        for err in errors().into_iter().skip(1) {
+            assert_eq!(err.code(), None, "{}", err);
+        }
+    }
+
+    #[test]
+    fn arrow_errors_are_not_wrapped_twice() {
+        let err = FlightSqlError::from(ArrowError::ComputeError("overflow".to_string()));
+        assert!(matches!(
+            &err,
+            FlightSqlError::Arrow(ArrowError::ComputeError(_))
+        ));
+        assert!(matches!(ArrowError::from(err), ArrowError::ComputeError(_)));
+
+        // Other external errors are kept as they are.
+        let err = FlightSqlError::from(ArrowError::ExternalError("other".into()));
+        assert!(matches!(
+            &err,
+            FlightSqlError::Arrow(ArrowError::ExternalError(_))
+        ));
+        assert!(FlightSqlError::downcast_ref(&ArrowError::from(err)).is_none());
+    }
+}
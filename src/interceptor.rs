@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use tonic::{
+    metadata::{Ascii, MetadataKey, MetadataValue},
+    service::Interceptor,
+    Request, Status,
+};
+
+use crate::error::{FlightSqlError, Result};
+use crate::tracing::tracing_current_span_to_req;
+
+/// The header used to send the credentials and the bearer token.
+pub static AUTHORIZATION_HEADER: &str = "authorization";
+
+/// The header used to send the deadline of a call to the server.
+pub static GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Marks the request of a stream, such as DoGet, that is sent without the
+/// deadline: servers apply it to the whole stream, which would end results
+/// that take longer but still make progress. Those are bounded by the idle
+/// timeout of the stream instead.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WithoutDeadline;
+
+/// A tonic Interceptor that adds a set of headers, such as the bearer token
+/// used to authenticate, and the deadline of the call to every request, but
+/// those marked `WithoutDeadline`.
+///
+/// All clones share the same headers, so a token set on one of them is sent
+/// by every client created from the same connection.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderInterceptor {
+    headers: Arc<RwLock<HashMap<MetadataKey<Ascii>, MetadataValue<Ascii>>>>,
+    timeout: Arc<RwLock<Option<Duration>>>,
+}
+
+impl HeaderInterceptor {
+    /// Send the header with every request, replacing a previous value.
+    #[tracing::instrument(level = "debug", skip(self, value))]
+    pub fn insert(&self, key: &str, value: &str) -> Result<()> {
+        let key = MetadataKey::from_str(key).map_err(|e| {
+            FlightSqlError::InvalidArgument(format!("Invalid header name {}: {}", key, e))
+        })?;
+        let value = MetadataValue::try_from(value).map_err(|e| {
+            FlightSqlError::InvalidArgument(format!("Invalid value for header {}: {}", key, e))
+        })?;
+        self.headers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, value);
+        Ok(())
+    }
+
+    /// The value of a header that is sent with every request.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get(&self, key: &str) -> Option<String> {
+        let key = MetadataKey::from_str(key).ok()?;
+        self.headers
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    }
+
+    /// Send the bearer token with every request.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn set_token(&self, token: &str) -> Result<()> {
+        self.insert(AUTHORIZATION_HEADER, &format!("Bearer {}", token))
+    }
+
+    /// The bearer token that is sent with every request.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn token(&self) -> Option<String> {
+        bearer_token(&self.get(AUTHORIZATION_HEADER)?).map(str::to_string)
+    }
+
+    /// Send the timeout as the `grpc-timeout` deadline of every request but
+    /// those of streams.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        *self.timeout.write().unwrap_or_else(PoisonError::into_inner) = timeout;
+    }
+
+    /// The deadline that is sent with every request.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn timeout(&self) -> Option<Duration> {
+        *self.timeout.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A copy of the headers and the deadline, which is not shared with this
+    /// interceptor.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn detach(&self) -> Self {
+        let headers = self.headers.read().unwrap_or_else(PoisonError::into_inner);
+        HeaderInterceptor {
+            headers: Arc::new(RwLock::new(headers.clone())),
+            timeout: Arc::new(RwLock::new(self.timeout())),
+        }
+    }
+}
+
+impl Interceptor for HeaderInterceptor {
+    /// Add the headers and the deadline that are not already set on the
+    /// request, the deadline only when it is not marked `WithoutDeadline`.
+    /// Also add the context of the current span, so that the spans of the
+    /// server are linked to those of the client.
+    fn call(&mut self, mut request: Request<()>) -> std::result::Result<Request<()>, Status> {
+        let headers = self.headers.read().unwrap_or_else(PoisonError::into_inner);
+        let metadata = request.metadata_mut();
+        for (key, value) in headers.iter() {
+            if !metadata.contains_key(key) {
+                metadata.insert(key.clone(), value.clone());
+            }
+        }
+        if let Some(timeout) = self.timeout() {
+            if !request.metadata().contains_key(GRPC_TIMEOUT_HEADER)
+                && request.extensions().get::<WithoutDeadline>().is_none()
+            {
+                request.set_timeout(timeout);
+            }
+        }
+        tracing_current_span_to_req(&mut request);
+        Ok(request)
+    }
+}
+
+/// Extract the token from the value of an `authorization: Bearer` header.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Some(token.trim())
+    } else {
+        None
+    }
+}
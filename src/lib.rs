@@ -2,7 +2,16 @@
 pub mod arrow_flight_protocol;
 
 #[path = "arrow.flight.protocol.sql.rs"]
+#[allow(clippy::doc_lazy_continuation, clippy::doc_overindented_list_items)]
 pub mod arrow_flight_protocol_sql;
 
+pub mod capabilities;
 pub mod client;
+pub mod connection;
+pub mod error;
+pub mod interceptor;
+pub mod metrics;
+pub mod retry;
+pub mod sql_info;
 pub mod tracing;
+mod validation;
@@ -0,0 +1,214 @@
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram, Unit},
+    Context, KeyValue,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+use tonic::{Code, Status};
+
+/// The calls made by a client and the data decoded from their responses,
+/// since the stats were last taken.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientStats {
+    /// The number of GetFlightInfo calls.
+    pub get_flight_info_calls: u64,
+    /// The time spent waiting for the responses of GetFlightInfo.
+    pub get_flight_info_time: Duration,
+    /// The number of DoGet streams that were opened.
+    pub do_get_calls: u64,
+    /// The shortest time from a DoGet call to the first batch of its stream.
+    pub time_to_first_batch: Option<Duration>,
+    /// The longest time from a DoGet call to the end of its stream.
+    pub do_get_time: Duration,
+    /// The number of rows decoded from the DoGet streams.
+    pub rows: u64,
+    /// The number of batches decoded from the DoGet streams.
+    pub batches: u64,
+    /// The size of the messages of the DoGet streams.
+    pub bytes: u64,
+    /// The number of failed calls by gRPC status code.
+    pub errors: HashMap<Code, u64>,
+}
+
+/// The OpenTelemetry instruments the calls of a client are recorded in.
+#[derive(Debug)]
+struct Instruments {
+    get_flight_info_duration: Histogram<f64>,
+    time_to_first_batch: Histogram<f64>,
+    do_get_duration: Histogram<f64>,
+    rows: Counter<u64>,
+    batches: Counter<u64>,
+    bytes: Counter<u64>,
+    errors: Counter<u64>,
+}
+
+/// Records the calls of a client, both in OpenTelemetry instruments and in
+/// the stats of the client.
+///
+/// The instruments are created with the global meter provider, so they only
+/// export anything when the application installed a provider before the
+/// client was created. Clones share the same stats.
+#[derive(Debug, Clone)]
+pub(crate) struct ClientMetrics {
+    instruments: Arc<Instruments>,
+    stats: Arc<Mutex<ClientStats>>,
+}
+
+impl Default for ClientMetrics {
+    fn default() -> Self {
+        let meter = global::meter(env!("CARGO_PKG_NAME"));
+        let instruments = Instruments {
+            get_flight_info_duration: meter
+                .f64_histogram("flight_sql.client.get_flight_info.duration")
+                .with_description("The time spent waiting for the response of GetFlightInfo")
+                .with_unit(Unit::new("ms"))
+                .init(),
+            time_to_first_batch: meter
+                .f64_histogram("flight_sql.client.do_get.time_to_first_batch")
+                .with_description("The time from a DoGet call to the first batch of its stream")
+                .with_unit(Unit::new("ms"))
+                .init(),
+            do_get_duration: meter
+                .f64_histogram("flight_sql.client.do_get.duration")
+                .with_description("The time from a DoGet call to the end of its stream")
+                .with_unit(Unit::new("ms"))
+                .init(),
+            rows: meter
+                .u64_counter("flight_sql.client.rows")
+                .with_description("The number of rows decoded from DoGet streams")
+                .init(),
+            batches: meter
+                .u64_counter("flight_sql.client.batches")
+                .with_description("The number of batches decoded from DoGet streams")
+                .init(),
+            bytes: meter
+                .u64_counter("flight_sql.client.bytes")
+                .with_description("The size of the messages of DoGet streams")
+                .with_unit(Unit::new("By"))
+                .init(),
+            errors: meter
+                .u64_counter("flight_sql.client.errors")
+                .with_description("The number of failed calls by method and gRPC status code")
+                .init(),
+        };
+        ClientMetrics {
+            instruments: Arc::new(instruments),
+            stats: Default::default(),
+        }
+    }
+}
+
+impl ClientMetrics {
+    /// The stats recorded since they were last taken.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn stats(&self) -> ClientStats {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Take the stats recorded so far, and start over.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn take_stats(&self) -> ClientStats {
+        std::mem::take(&mut *self.stats.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Count the error of a failed call.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn record_status<T>(&self, method: &'static str, result: &Result<T, Status>) {
+        if let Err(status) = result {
+            self.instruments.errors.add(
+                &Context::current(),
+                1,
+                &[
+                    KeyValue::new("rpc.method", method),
+                    KeyValue::new("rpc.grpc.status_code", status.code() as i64),
+                ],
+            );
+            *self
+                .stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .errors
+                .entry(status.code())
+                .or_default() += 1;
+        }
+    }
+
+    /// Record a GetFlightInfo call that returned after `elapsed`.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn record_get_flight_info(&self, elapsed: Duration) {
+        self.instruments.get_flight_info_duration.record(
+            &Context::current(),
+            milliseconds(elapsed),
+            &[],
+        );
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+        stats.get_flight_info_calls += 1;
+        stats.get_flight_info_time += elapsed;
+    }
+
+    /// Count a DoGet stream that was opened, once per call however many
+    /// attempts it took.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn record_do_get(&self) {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .do_get_calls += 1;
+    }
+
+    /// Record the size of a message of a DoGet stream.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn record_message(&self, bytes: usize) {
+        self.instruments
+            .bytes
+            .add(&Context::current(), bytes as u64, &[]);
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .bytes += bytes as u64;
+    }
+
+    /// Record a batch decoded from a DoGet stream, which is the first batch
+    /// of the stream when `first_batch_after` is set.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn record_batch(&self, rows: usize, first_batch_after: Option<Duration>) {
+        let cx = Context::current();
+        self.instruments.batches.add(&cx, 1, &[]);
+        self.instruments.rows.add(&cx, rows as u64, &[]);
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+        stats.batches += 1;
+        stats.rows += rows as u64;
+        if let Some(elapsed) = first_batch_after {
+            self.instruments
+                .time_to_first_batch
+                .record(&cx, milliseconds(elapsed), &[]);
+            stats.time_to_first_batch = Some(
+                stats
+                    .time_to_first_batch
+                    .map_or(elapsed, |shortest| shortest.min(elapsed)),
+            );
+        }
+    }
+
+    /// Record the end of a DoGet stream.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn record_do_get_end(&self, elapsed: Duration) {
+        self.instruments
+            .do_get_duration
+            .record(&Context::current(), milliseconds(elapsed), &[]);
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+        stats.do_get_time = stats.do_get_time.max(elapsed);
+    }
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
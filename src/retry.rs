@@ -0,0 +1,150 @@
+use std::{future::Future, time::Duration};
+
+use tonic::Code;
+
+use crate::error::{FlightSqlError, Result};
+
+/// How the calls that are safe to repeat, the metadata requests and DoGet,
+/// are retried when the server is unavailable, such as during a restart.
+///
+/// Calls that may change data, such as updates, are never retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The number of times a call is made at most, 1 to never retry.
+    pub max_attempts: u32,
+    /// The time waited before the first retry.
+    pub initial_backoff: Duration,
+    /// The longest time waited before a retry.
+    pub max_backoff: Duration,
+    /// The factor the backoff grows by after every retry.
+    pub multiplier: f64,
+    /// The fraction of the backoff that is random, between 0 and 1, so that
+    /// clients that failed together do not retry together.
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The time to wait before the given retry, starting at 1.
+    ///
+    /// A multiplier below 1 or a jitter outside of 0 to 1, including NaN, are
+    /// treated as the nearest valid value, so that no policy can panic.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let multiplier = self.multiplier.max(1.0);
+        let backoff = self.initial_backoff.as_secs_f64()
+            * multiplier.powi(retry.saturating_sub(1).min(i32::MAX as u32) as i32);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        let jitter = match self.jitter.is_nan() {
+            true => 0.0,
+            false => self.jitter.clamp(0.0, 1.0),
+        };
+        let jitter = jitter * rand::random::<f64>();
+        Duration::try_from_secs_f64(backoff * (1.0 - jitter)).unwrap_or(self.max_backoff)
+    }
+
+    /// Whether a call that failed with the error is retried.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn is_transient(err: &FlightSqlError) -> bool {
+        err.code() == Some(Code::Unavailable)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+/// Make the call until it succeeds, fails with an error that is not
+/// transient, or the attempts of the policy run out, waiting longer between
+/// every attempt.
+#[tracing::instrument(level = "debug", skip(policy, call))]
+pub(crate) async fn retry<T, F, Fut>(policy: &RetryPolicy, method: &str, mut call: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Err(err) if attempt < policy.max_attempts && RetryPolicy::is_transient(&err) => {
+                let backoff = policy.backoff(attempt);
+                tracing::warn!(
+                    attempt,
+                    max_attempts = policy.max_attempts,
+                    backoff_ms = backoff.as_millis() as u64,
+                    "{} failed, retrying: {}",
+                    method,
+                    err
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(100), Duration::from_secs(10));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn jitter_shortens_the_backoff() {
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!(backoff <= Duration::from_millis(100));
+            assert!(backoff >= Duration::from_millis(80));
+        }
+    }
+
+    #[test]
+    fn invalid_policy_does_not_panic() {
+        for (multiplier, jitter) in [
+            (-2.0, 0.0),
+            (f64::NAN, 0.0),
+            (f64::INFINITY, 0.0),
+            (2.0, f64::NAN),
+            (2.0, -1.0),
+            (2.0, 5.0),
+            (f64::NAN, f64::NAN),
+        ] {
+            let policy = RetryPolicy {
+                multiplier,
+                jitter,
+                ..Default::default()
+            };
+            for retry in [0, 1, 2, 3, u32::MAX] {
+                assert!(policy.backoff(retry) <= policy.max_backoff);
+            }
+        }
+    }
+}
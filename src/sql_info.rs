@@ -0,0 +1,223 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use arrow::{
+    array::{
+        Array, ArrayRef, BooleanArray, Int32Array, Int64Array, ListArray, MapArray, StringArray,
+        StructArray, UInt32Array, UnionArray,
+    },
+    datatypes::{DataType, Field, Schema},
+    error::{ArrowError, Result},
+    record_batch::RecordBatch,
+};
+
+use crate::arrow_flight_protocol_sql::SqlInfo;
+
+/// The value of a SqlInfo, decoded from the dense union in the `value` column
+/// returned by GetSqlInfo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlInfoValue {
+    String(String),
+    Bool(bool),
+    BigInt(i64),
+    Int32Bitmask(i32),
+    StringList(Vec<String>),
+    Int32ToInt32ListMap(BTreeMap<i32, Vec<i32>>),
+}
+
+impl fmt::Display for SqlInfoValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlInfoValue::String(value) => write!(f, "{}", value),
+            SqlInfoValue::Bool(value) => write!(f, "{}", value),
+            SqlInfoValue::BigInt(value) => write!(f, "{}", value),
+            SqlInfoValue::Int32Bitmask(value) => write!(f, "{}", value),
+            SqlInfoValue::StringList(values) => write!(f, "[{}]", values.join(", ")),
+            SqlInfoValue::Int32ToInt32ListMap(map) => {
+                let entries = map
+                    .iter()
+                    .map(|(key, values)| format!("{}: {:?}", key, values))
+                    .collect::<Vec<_>>();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+        }
+    }
+}
+
+/// The name of a SqlInfo, such as `FLIGHT_SQL_SERVER_NAME`, or its number when
+/// it is not defined in FlightSql.proto.
+#[tracing::instrument(level = "debug")]
+pub fn sql_info_name(info: u32) -> String {
+    i32::try_from(info)
+        .ok()
+        .and_then(SqlInfo::from_i32)
+        .map(|sql_info| sql_info.as_str_name().to_string())
+        .unwrap_or_else(|| info.to_string())
+}
+
+/// Parse the name of a SqlInfo, with or without its `SQL_` or `FLIGHT_SQL_`
+/// prefix, or its number.
+#[tracing::instrument(level = "debug")]
+pub fn parse_sql_info(name: &str) -> Result<SqlInfo> {
+    let upper = name.trim().to_ascii_uppercase();
+    name.trim()
+        .parse::<i32>()
+        .ok()
+        .and_then(SqlInfo::from_i32)
+        .or_else(|| SqlInfo::from_str_name(&upper))
+        .or_else(|| SqlInfo::from_str_name(&format!("SQL_{}", upper)))
+        .or_else(|| SqlInfo::from_str_name(&format!("FLIGHT_SQL_{}", upper)))
+        .ok_or_else(|| ArrowError::InvalidArgumentError(format!("Unknown SqlInfo: {}", name)))
+}
+
+/// Decode the `info_name` and `value` columns of a RecordBatch returned by
+/// GetSqlInfo, in the order of its rows.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn decode_sql_info(batch: &RecordBatch) -> Result<Vec<(u32, SqlInfoValue)>> {
+    let names = column::<UInt32Array>(batch, 0, "info_name")?;
+    let values = column::<UnionArray>(batch, 1, "value")?;
+    // The offsets of the union come from the server, and are checked before
+    // they are used to slice its children.
+    values.data().validate_full()?;
+    (0..batch.num_rows())
+        .map(|row| {
+            let value = union_value(values, row)?;
+            Ok((names.value(row), decode_sql_info_value(&value)?))
+        })
+        .collect()
+}
+
+/// Decode SqlInfos from several RecordBatches, keyed by SqlInfo number.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn decode_sql_info_batches(batches: &[RecordBatch]) -> Result<BTreeMap<u32, SqlInfoValue>> {
+    let mut infos = BTreeMap::new();
+    for batch in batches {
+        infos.extend(decode_sql_info(batch)?);
+    }
+    Ok(infos)
+}
+
+/// Create a RecordBatch with readable `name` and `value` string columns.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn sql_info_name_value_batch(infos: &[(u32, SqlInfoValue)]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("value", DataType::Utf8, false),
+    ]);
+    let names: StringArray = infos
+        .iter()
+        .map(|(info, _)| Some(sql_info_name(*info)))
+        .collect();
+    let values: StringArray = infos
+        .iter()
+        .map(|(_, value)| Some(value.to_string()))
+        .collect();
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Arc::new(names) as ArrayRef, Arc::new(values)],
+    )
+}
+
+/// Decode a single value of the dense union, based on the type of its child.
+#[tracing::instrument(level = "debug", skip_all)]
+fn decode_sql_info_value(value: &ArrayRef) -> Result<SqlInfoValue> {
+    if value.is_empty() {
+        return Err(ArrowError::InvalidArgumentError(
+            "Missing SqlInfo value".to_string(),
+        ));
+    }
+    match value.data_type() {
+        DataType::Utf8 => Ok(SqlInfoValue::String(
+            downcast::<StringArray>(value)?.value(0).to_string(),
+        )),
+        DataType::Boolean => Ok(SqlInfoValue::Bool(
+            downcast::<BooleanArray>(value)?.value(0),
+        )),
+        DataType::Int64 => Ok(SqlInfoValue::BigInt(
+            downcast::<Int64Array>(value)?.value(0),
+        )),
+        DataType::Int32 => Ok(SqlInfoValue::Int32Bitmask(
+            downcast::<Int32Array>(value)?.value(0),
+        )),
+        DataType::List(_) => {
+            let list = downcast::<ListArray>(value)?.value(0);
+            let strings = downcast::<StringArray>(&list)?;
+            Ok(SqlInfoValue::StringList(
+                strings.iter().flatten().map(str::to_string).collect(),
+            ))
+        }
+        DataType::Map(_, _) => {
+            let entries = downcast::<MapArray>(value)?.value(0);
+            let entries = downcast::<StructArray>(&entries)?;
+            let columns = entries.columns();
+            let (keys, lists) = match columns[..] {
+                [keys, lists] => (downcast::<Int32Array>(keys)?, downcast::<ListArray>(lists)?),
+                _ => {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Expected a map with keys and values in SqlInfo, found {} columns",
+                        columns.len()
+                    )))
+                }
+            };
+            let mut map = BTreeMap::new();
+            for i in 0..entries.len() {
+                let list = lists.value(i);
+                let values = downcast::<Int32Array>(&list)?;
+                map.insert(keys.value(i), values.iter().flatten().collect());
+            }
+            Ok(SqlInfoValue::Int32ToInt32ListMap(map))
+        }
+        data_type => Err(ArrowError::InvalidArgumentError(format!(
+            "Unexpected SqlInfo value type: {:?}",
+            data_type
+        ))),
+    }
+}
+
+/// The value of a row of the union, like `UnionArray::value`, but with an
+/// error instead of a panic when its type id or offset is out of range.
+#[tracing::instrument(level = "debug", skip_all)]
+fn union_value(values: &UnionArray, row: usize) -> Result<ArrayRef> {
+    let type_id = values.type_id(row);
+    let offset = values.value_offset(row);
+    let child = usize::try_from(type_id)
+        .ok()
+        .filter(|type_id| *type_id < values.data().child_data().len())
+        .map(|type_id| values.child(type_id as i8))
+        .ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!(
+                "Invalid SqlInfo value type id {} in row {}",
+                type_id, row
+            ))
+        })?;
+    usize::try_from(offset)
+        .ok()
+        .filter(|offset| *offset < child.len())
+        .map(|offset| child.slice(offset, 1))
+        .ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!(
+                "Invalid SqlInfo value offset {} in row {}",
+                offset, row
+            ))
+        })
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn column<'a, T: 'static>(batch: &'a RecordBatch, index: usize, name: &str) -> Result<&'a T> {
+    batch
+        .columns()
+        .get(index)
+        .and_then(|column| column.as_any().downcast_ref::<T>())
+        .ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!("Unexpected {} column in SqlInfo", name))
+        })
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn downcast<T: 'static>(array: &ArrayRef) -> Result<&T> {
+    array.as_any().downcast_ref::<T>().ok_or_else(|| {
+        ArrowError::InvalidArgumentError(format!(
+            "Unexpected SqlInfo value type: {:?}",
+            array.data_type()
+        ))
+    })
+}
@@ -1,71 +1,226 @@
+use futures::future::BoxFuture;
 use opentelemetry::{
     global,
     propagation::Injector,
-    runtime::Tokio,
-    sdk::{propagation::TraceContextPropagator, trace, trace::Tracer, Resource},
+    sdk::{
+        export::trace::{stdout, ExportResult, SpanData, SpanExporter},
+        trace::{self, Sampler, TracerProvider},
+        Resource,
+    },
+    trace::TraceError,
     KeyValue,
 };
-use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_semantic_conventions::resource::SERVICE_NAME;
-use std::str::FromStr;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tonic::{
     metadata::{MetadataKey, MetadataMap},
-    Request,
+    Code, Request, Status,
 };
-use tracing::{debug, subscriber};
+use tracing::{field, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
-use tracing_subscriber::{filter, layer::SubscriberExt, EnvFilter, Layer, Registry};
-
-///  Create the opentelemetry::sdk::trace::Tracer to use in the telemetry layer.
-/// * `otlp_endpoint` - The opentelemetry collector endpoint.
-fn create_opentelemetry_tracer(otlp_endpoint: String) -> Tracer {
-    opentelemetry_otlp::new_pipeline()
-        .tracing()
-        .with_exporter(
-            opentelemetry_otlp::new_exporter()
-                .tonic()
-                .with_endpoint(otlp_endpoint),
-        )
-        .with_trace_config(
-            trace::config().with_resource(Resource::new(vec![KeyValue::new(
-                SERVICE_NAME,
-                env!("CARGO_PKG_NAME"),
-            )])),
-        )
-        .install_batch(Tokio)
-        .unwrap()
+
+use crate::error::{FlightSqlError, Result};
+
+/// The endpoint of a local OpenTelemetry collector for OTLP over gRPC.
+pub const DEFAULT_OTLP_GRPC_ENDPOINT: &str = "http://localhost:4317";
+/// The endpoint of a local OpenTelemetry collector for OTLP over HTTP.
+pub const DEFAULT_OTLP_HTTP_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+
+/// Where spans are exported to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceExporter {
+    /// Spans are not exported.
+    None,
+    /// Each span is printed on a single line on stderr, to keep it apart from
+    /// the results on stdout.
+    Stderr,
+    /// Each span is printed over several lines on stderr.
+    Pretty,
+    /// Each span is written as a JSON object on its own line in the file.
+    JsonFile(PathBuf),
+    /// Spans are sent to the collector at the endpoint with OTLP over gRPC,
+    /// which requires the `otlp` feature.
+    OtlpGrpc(String),
+    /// Spans are sent to the collector at the endpoint with OTLP over HTTP,
+    /// which requires the `otlp` feature.
+    OtlpHttp(String),
+}
+
+/// How spans are sampled and exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryOptions {
+    pub exporter: TraceExporter,
+    /// The fraction of the traces that are sampled, between 0 and 1. The
+    /// traces started by a sampled parent are always sampled.
+    pub sample_ratio: f64,
+}
+
+impl Default for TelemetryOptions {
+    fn default() -> Self {
+        TelemetryOptions {
+            exporter: TraceExporter::None,
+            sample_ratio: 1.0,
+        }
+    }
 }
 
-///  Creates the tracing layers and inits the tracing subscriber.
-/// * `otlp_endpoint` - The opentelemetry collector endpoint.
-pub async fn setup_tracing(otlp_endpoint: &String) {
-    global::set_text_map_propagator(TraceContextPropagator::new());
+/// Create a TracerProvider that exports spans as configured, or None when
+/// spans are not exported.
+///
+/// Nothing is installed globally, the caller decides how the provider is
+/// used, such as in a `tracing_opentelemetry` layer of its own subscriber.
+#[tracing::instrument(level = "debug")]
+pub fn tracer_provider(options: &TelemetryOptions) -> Result<Option<TracerProvider>> {
+    if !(0.0..=1.0).contains(&options.sample_ratio) {
+        return Err(FlightSqlError::InvalidArgument(format!(
+            "The sample ratio {} is not between 0 and 1",
+            options.sample_ratio
+        )));
+    }
+    let config = trace::config()
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            options.sample_ratio,
+        ))))
+        .with_resource(Resource::new(vec![KeyValue::new(
+            SERVICE_NAME,
+            env!("CARGO_PKG_NAME"),
+        )]));
+    let builder = TracerProvider::builder().with_config(config);
+
+    let builder = match &options.exporter {
+        TraceExporter::None => return Ok(None),
+        TraceExporter::Stderr => {
+            builder.with_simple_exporter(stdout::Exporter::new(io::stderr(), false))
+        }
+        TraceExporter::Pretty => {
+            builder.with_simple_exporter(stdout::Exporter::new(io::stderr(), true))
+        }
+        TraceExporter::JsonFile(path) => {
+            builder.with_simple_exporter(JsonFileExporter::create(path)?)
+        }
+        #[cfg(feature = "otlp")]
+        TraceExporter::OtlpGrpc(endpoint) => {
+            use opentelemetry_otlp::WithExportConfig;
+            let exporter = opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint);
+            builder.with_batch_exporter(otlp_exporter(exporter)?, opentelemetry::runtime::Tokio)
+        }
+        #[cfg(feature = "otlp")]
+        TraceExporter::OtlpHttp(endpoint) => {
+            use opentelemetry_otlp::WithExportConfig;
+            let exporter = opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint);
+            builder.with_batch_exporter(otlp_exporter(exporter)?, opentelemetry::runtime::Tokio)
+        }
+        #[cfg(not(feature = "otlp"))]
+        TraceExporter::OtlpGrpc(_) | TraceExporter::OtlpHttp(_) => {
+            return Err(FlightSqlError::InvalidArgument(
+                "The OTLP exporters require the otlp feature, which is not enabled".to_string(),
+            ))
+        }
+    };
+    Ok(Some(builder.build()))
+}
 
-    let env_filter = EnvFilter::try_from_default_env()
-        .or_else(|_| EnvFilter::try_new("warn,arrow_flight_sql_client=debug"))
-        .unwrap();
+#[cfg(feature = "otlp")]
+#[tracing::instrument(level = "debug", skip_all)]
+fn otlp_exporter(
+    builder: impl Into<opentelemetry_otlp::SpanExporterBuilder>,
+) -> Result<opentelemetry_otlp::SpanExporter> {
+    builder.into().build_span_exporter().map_err(|err| {
+        FlightSqlError::InvalidArgument(format!("Can not create the OTLP exporter: {}", err))
+    })
+}
 
-    let log_layer = tracing_subscriber::fmt::layer();
+/// A SpanExporter that writes each span as a JSON object on its own line.
+#[derive(Debug)]
+pub struct JsonFileExporter {
+    writer: BufWriter<File>,
+}
 
-    let tracer = create_opentelemetry_tracer(otlp_endpoint.to_string());
+impl JsonFileExporter {
+    /// Create the file, replacing any existing file.
+    #[tracing::instrument(level = "debug")]
+    pub fn create(path: &PathBuf) -> Result<Self> {
+        let file = File::create(path).map_err(|err| {
+            FlightSqlError::InvalidArgument(format!(
+                "Can not create the trace file {}: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        Ok(JsonFileExporter {
+            writer: BufWriter::new(file),
+        })
+    }
 
-    let telemetry_layer = tracing_opentelemetry::layer()
-        .with_tracer(tracer)
-        .with_exception_field_propagation(true)
-        .with_tracked_inactivity(true)
-        .with_filter(filter::LevelFilter::INFO);
+    fn write(&mut self, batch: &[SpanData]) -> io::Result<()> {
+        for span in batch {
+            serde_json::to_writer(&mut self.writer, &span_to_json(span))?;
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.flush()
+    }
+}
 
-    let collector = Registry::default()
-        .with(env_filter)
-        .with(log_layer)
-        .with(telemetry_layer);
+impl SpanExporter for JsonFileExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let result = self
+            .write(&batch)
+            .map_err(|err| TraceError::from(format!("Can not write the spans: {}", err)));
+        Box::pin(std::future::ready(result))
+    }
+}
 
-    subscriber::set_global_default(collector).unwrap();
+fn span_to_json(span: &SpanData) -> serde_json::Value {
+    let attributes = span
+        .attributes
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.as_str().into()))
+        .collect::<serde_json::Map<_, _>>();
+    let events = span
+        .events
+        .iter()
+        .map(|event| {
+            let attributes = event
+                .attributes
+                .iter()
+                .map(|kv| (kv.key.to_string(), kv.value.as_str().into()))
+                .collect::<serde_json::Map<_, _>>();
+            serde_json::json!({
+                "name": event.name,
+                "time_unix_nano": unix_nanos(event.timestamp),
+                "attributes": attributes,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "trace_id": span.span_context.trace_id().to_string(),
+        "span_id": span.span_context.span_id().to_string(),
+        "parent_span_id": span.parent_span_id.to_string(),
+        "name": span.name,
+        "kind": format!("{:?}", span.span_kind),
+        "start_time_unix_nano": unix_nanos(span.start_time),
+        "end_time_unix_nano": unix_nanos(span.end_time),
+        "attributes": attributes,
+        "events": events,
+        "status": format!("{:?}", span.status),
+    })
+}
 
-    debug!(
-        "Telemetry subscriber initiated for the OpenTelemetry endpoint [{}].",
-        otlp_endpoint
-    );
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|duration| u64::try_from(duration.as_nanos()).ok())
+        .unwrap_or_default()
 }
 
 pub struct MetadataInjector<'a>(&'a mut MetadataMap);
@@ -81,9 +236,59 @@ impl<'a> Injector for MetadataInjector<'a> {
     }
 }
 
+/// Inject the context of the current span, such as the W3C `traceparent`
+/// and `baggage` headers, into the metadata of the request, with the global
+/// propagator set by the application.
 pub fn tracing_current_span_to_req<T>(request: &mut Request<T>) {
     let cx = tracing::Span::current().context();
     global::get_text_map_propagator(|propagator| {
         propagator.inject_context(&cx, &mut MetadataInjector(request.metadata_mut()))
     });
 }
+
+/// The service of the Flight RPCs, as in their path.
+const FLIGHT_SERVICE: &str = "arrow.flight.protocol.FlightService";
+
+/// Create the client span of a call to the Flight service, with the
+/// attributes of the OpenTelemetry semantic conventions for RPC, and the size
+/// of the encoded request.
+///
+/// The status code and the size of the response are recorded once they are
+/// known, with `record_status` and `record_response`.
+pub(crate) fn rpc_span(method: &'static str, request_size: usize) -> Span {
+    tracing::info_span!(
+        "rpc",
+        otel.name = %format!("{}/{}", FLIGHT_SERVICE, method),
+        otel.kind = "client",
+        otel.status_code = field::Empty,
+        otel.status_message = field::Empty,
+        rpc.system = "grpc",
+        rpc.service = FLIGHT_SERVICE,
+        rpc.method = method,
+        rpc.grpc.status_code = field::Empty,
+        rpc.request.size = request_size,
+        rpc.response.size = field::Empty,
+        flight.batches = field::Empty,
+        flight.records = field::Empty,
+    )
+}
+
+/// Record the status code of the call, or of the stream of its response, in
+/// its span.
+pub(crate) fn record_status<T>(span: &Span, result: &std::result::Result<T, Status>) {
+    match result {
+        Ok(_) => {
+            span.record("rpc.grpc.status_code", Code::Ok as i32);
+        }
+        Err(status) => {
+            span.record("rpc.grpc.status_code", status.code() as i32);
+            span.record("otel.status_code", "ERROR");
+            span.record("otel.status_message", status.message());
+        }
+    }
+}
+
+/// Record the size of the encoded response in the span of the call.
+pub(crate) fn record_response(span: &Span, response_size: usize) {
+    span.record("rpc.response.size", response_size);
+}
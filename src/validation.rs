@@ -0,0 +1,713 @@
+//! Checked reading of the Arrow IPC messages sent by a server.
+//!
+//! Arrow 24 trusts the schema and the buffers of an IPC message, and panics
+//! on many kinds of invalid data, such as buffers out of the bounds of the
+//! body, missing field nodes or offsets out of the bounds of the values. The
+//! schemas are checked before arrow converts them, and the record batches are
+//! read here instead of by arrow, with the checks of `ArrayData::try_new`, so
+//! that invalid data is returned as a `FlightSqlError::Decode` instead.
+
+use std::collections::HashMap;
+
+use arrow::{
+    array::{as_primitive_array, make_array, Array, ArrayData, ArrayRef, DictionaryArray},
+    buffer::Buffer,
+    compute::cast,
+    datatypes::{
+        DataType, Field, Int16Type, Int32Type, Int64Type, Int8Type, IntervalUnit, Schema,
+        SchemaRef, UInt16Type, UInt32Type, UInt64Type, UInt8Type, UnionMode,
+    },
+    error::{ArrowError, Result as ArrowResult},
+    ipc,
+    record_batch::{RecordBatch, RecordBatchOptions},
+};
+
+use crate::error::FlightSqlError;
+
+/// The largest number of values in an array, which keeps the sizes computed
+/// from the lengths sent by the server from overflowing.
+const MAX_LENGTH: i64 = i32::MAX as i64;
+
+/// The alignment of buffers that arrow reads as 128 bit values.
+const I128_ALIGNMENT: usize = std::mem::align_of::<i128>();
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn invalid(message: impl Into<String>) -> ArrowError {
+    FlightSqlError::Decode(message.into().into()).into()
+}
+
+/// Check that arrow can convert the schema.
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) fn validate_schema(schema: ipc::Schema) -> ArrowResult<()> {
+    let fields = schema
+        .fields()
+        .ok_or_else(|| invalid("The schema has no fields"))?;
+    for field in fields.iter() {
+        if field.type_type() == ipc::Type::Decimal && schema.endianness() == ipc::Endianness::Big {
+            return Err(invalid("Big endian decimals are not supported"));
+        }
+        validate_field(field)?;
+    }
+    Ok(())
+}
+
+/// Check that arrow can convert the field and its children.
+#[tracing::instrument(level = "debug", skip_all)]
+fn validate_field(field: ipc::Field) -> ArrowResult<()> {
+    let name = field
+        .name()
+        .ok_or_else(|| invalid("A field of the schema has no name"))?;
+    let unsupported = || {
+        invalid(format!(
+            "Field {} has an invalid or unsupported type {:?}",
+            name,
+            field.type_type()
+        ))
+    };
+    if let Some(dictionary) = field.dictionary() {
+        let index_type = dictionary.indexType().ok_or_else(unsupported)?;
+        if !matches!(index_type.bitWidth(), 8 | 16 | 32 | 64) {
+            return Err(unsupported());
+        }
+    }
+
+    let children = field.children().map_or(0, |children| children.len());
+    let valid = match field.type_type() {
+        ipc::Type::Null
+        | ipc::Type::Bool
+        | ipc::Type::Binary
+        | ipc::Type::LargeBinary
+        | ipc::Type::Utf8
+        | ipc::Type::LargeUtf8
+        | ipc::Type::Struct_ => true,
+        ipc::Type::Int => field
+            .type_as_int()
+            .is_some_and(|int| matches!(int.bitWidth(), 8 | 16 | 32 | 64)),
+        ipc::Type::FixedSizeBinary => field
+            .type_as_fixed_size_binary()
+            .is_some_and(|binary| binary.byteWidth() >= 0),
+        ipc::Type::FloatingPoint => field.type_as_floating_point().is_some_and(|float| {
+            matches!(
+                float.precision(),
+                ipc::Precision::HALF | ipc::Precision::SINGLE | ipc::Precision::DOUBLE
+            )
+        }),
+        ipc::Type::Date => field.type_as_date().is_some_and(|date| {
+            matches!(date.unit(), ipc::DateUnit::DAY | ipc::DateUnit::MILLISECOND)
+        }),
+        ipc::Type::Time => field.type_as_time().is_some_and(|time| {
+            matches!(
+                (time.bitWidth(), time.unit()),
+                (32, ipc::TimeUnit::SECOND)
+                    | (32, ipc::TimeUnit::MILLISECOND)
+                    | (64, ipc::TimeUnit::MICROSECOND)
+                    | (64, ipc::TimeUnit::NANOSECOND)
+            )
+        }),
+        ipc::Type::Timestamp => field
+            .type_as_timestamp()
+            .is_some_and(|timestamp| is_time_unit(timestamp.unit())),
+        ipc::Type::Duration => field
+            .type_as_duration()
+            .is_some_and(|duration| is_time_unit(duration.unit())),
+        ipc::Type::Interval => field.type_as_interval().is_some_and(|interval| {
+            matches!(
+                interval.unit(),
+                ipc::IntervalUnit::YEAR_MONTH
+                    | ipc::IntervalUnit::DAY_TIME
+                    | ipc::IntervalUnit::MONTH_DAY_NANO
+            )
+        }),
+        ipc::Type::List | ipc::Type::LargeList => children == 1,
+        ipc::Type::FixedSizeList => {
+            children == 1
+                && field
+                    .type_as_fixed_size_list()
+                    .is_some_and(|list| list.listSize() >= 0)
+        }
+        ipc::Type::Map => children == 1 && field.type_as_map().is_some(),
+        ipc::Type::Decimal => field.type_as_decimal().is_some_and(|decimal| {
+            matches!(decimal.bitWidth(), 128 | 256)
+                && u8::try_from(decimal.precision()).is_ok()
+                && u8::try_from(decimal.scale()).is_ok()
+        }),
+        ipc::Type::Union => field.type_as_union().is_some_and(|union| {
+            matches!(union.mode(), ipc::UnionMode::Dense | ipc::UnionMode::Sparse)
+                && union.typeIds().is_none_or(|type_ids| {
+                    type_ids.len() == children
+                        && type_ids.iter().all(|type_id| i8::try_from(type_id).is_ok())
+                })
+        }),
+        _ => false,
+    };
+    if !valid {
+        return Err(unsupported());
+    }
+
+    for child in field.children().into_iter().flatten() {
+        validate_field(child)?;
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+fn is_time_unit(unit: ipc::TimeUnit) -> bool {
+    matches!(
+        unit,
+        ipc::TimeUnit::SECOND
+            | ipc::TimeUnit::MILLISECOND
+            | ipc::TimeUnit::MICROSECOND
+            | ipc::TimeUnit::NANOSECOND
+    )
+}
+
+/// Read a record batch, checking that its field nodes and buffers describe
+/// valid arrays of the fields of the schema, within the bounds of the body.
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) fn read_record_batch(
+    body: &Buffer,
+    batch: ipc::RecordBatch,
+    schema: SchemaRef,
+    dictionaries_by_id: &HashMap<i64, ArrayRef>,
+    metadata: &ipc::MetadataVersion,
+) -> ArrowResult<RecordBatch> {
+    let mut reader = BatchReader::new(body, batch, dictionaries_by_id, metadata)?;
+    let columns = reader.read(&schema, false)?;
+    let options = RecordBatchOptions::new().with_row_count(Some(reader.length));
+    RecordBatch::try_new_with_options(schema, columns, &options)
+}
+
+/// Read the values of a dictionary batch like `read_record_batch`.
+///
+/// Dictionaries are concatenated by arrow when a delta arrives, which
+/// requires their buffers to hold whole values, so this is checked as well.
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) fn read_dictionary_values(
+    body: &Buffer,
+    batch: ipc::RecordBatch,
+    value_type: &DataType,
+    dictionaries_by_id: &HashMap<i64, ArrayRef>,
+    metadata: &ipc::MetadataVersion,
+) -> ArrowResult<ArrayRef> {
+    let schema = Schema::new(vec![Field::new("", value_type.clone(), true)]);
+    let mut reader = BatchReader::new(body, batch, dictionaries_by_id, metadata)?;
+    Ok(reader.read(&schema, true)?.remove(0))
+}
+
+/// Walks the field nodes and buffers of a record batch in the order arrow
+/// writes them, and builds the arrays they describe with the checks of
+/// `ArrayData::try_new`.
+struct BatchReader<'a> {
+    body: &'a Buffer,
+    length: usize,
+    nodes: &'a [ipc::FieldNode],
+    buffers: &'a [ipc::Buffer],
+    dictionaries_by_id: &'a HashMap<i64, ArrayRef>,
+    metadata: ipc::MetadataVersion,
+    node_index: usize,
+    buffer_index: usize,
+}
+
+impl<'a> BatchReader<'a> {
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn new(
+        body: &'a Buffer,
+        batch: ipc::RecordBatch<'a>,
+        dictionaries_by_id: &'a HashMap<i64, ArrayRef>,
+        metadata: &ipc::MetadataVersion,
+    ) -> ArrowResult<Self> {
+        if batch.compression().is_some() {
+            return Err(invalid("Compressed record batches are not supported"));
+        }
+        Ok(BatchReader {
+            body,
+            length: checked_length(batch.length(), "record batch")?,
+            nodes: batch
+                .nodes()
+                .ok_or_else(|| invalid("The record batch has no field nodes"))?,
+            buffers: batch
+                .buffers()
+                .ok_or_else(|| invalid("The record batch has no buffers"))?,
+            dictionaries_by_id,
+            metadata: *metadata,
+            node_index: 0,
+            buffer_index: 0,
+        })
+    }
+
+    /// Read the arrays of all the fields, and check that every field node and
+    /// buffer of the record batch belongs to one of them.
+    ///
+    /// When `strict` is set, the buffers of fixed width values must hold
+    /// whole values.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn read(&mut self, schema: &Schema, strict: bool) -> ArrowResult<Vec<ArrayRef>> {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| Ok(make_array(self.array(field, strict)?)))
+            .collect::<ArrowResult<Vec<_>>>()?;
+        if self.node_index != self.nodes.len() || self.buffer_index != self.buffers.len() {
+            return Err(invalid(format!(
+                "The record batch has {} field nodes and {} buffers, its schema {} and {}",
+                self.nodes.len(),
+                self.buffers.len(),
+                self.node_index,
+                self.buffer_index
+            )));
+        }
+        Ok(columns)
+    }
+
+    /// Read the array of the field.
+    ///
+    /// Arrow validates the children of lists fully, so their arrays are
+    /// read with `strict` set.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn array(&mut self, field: &Field, strict: bool) -> ArrowResult<ArrayData> {
+        let data_type = field.data_type();
+        let array = match data_type {
+            DataType::Null => {
+                let (length, nulls) = self.node(field)?;
+                if nulls != length {
+                    return Err(invalid(format!(
+                        "Null field {} has {} nulls in {} values",
+                        field.name(),
+                        nulls,
+                        length
+                    )));
+                }
+                ArrayData::builder(DataType::Null).len(length).build()?
+            }
+            DataType::Utf8 | DataType::Binary | DataType::LargeUtf8 | DataType::LargeBinary => {
+                let (length, nulls) = self.node(field)?;
+                let offset_width = match data_type {
+                    DataType::Utf8 | DataType::Binary => 4,
+                    _ => 8,
+                };
+                ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .null_bit_buffer(self.null_buffer(nulls)?)
+                    .add_buffer(self.typed_buffer(offset_width, offset_width)?)
+                    .add_buffer(self.buffer(1)?)
+                    .build()?
+            }
+            DataType::Boolean | DataType::FixedSizeBinary(_) => {
+                let (length, nulls) = self.node(field)?;
+                ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .null_bit_buffer(self.null_buffer(nulls)?)
+                    .add_buffer(self.buffer(1)?)
+                    .build()?
+            }
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => {
+                let (length, nulls) = self.node(field)?;
+                let width = match data_type {
+                    DataType::Decimal128(_, _) => 16,
+                    _ => 32,
+                };
+                // Arrow checks the precision of the values, reading them as
+                // 128 bit values.
+                ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .null_bit_buffer(self.null_buffer(nulls)?)
+                    .add_buffer(self.typed_buffer(width, I128_ALIGNMENT)?)
+                    .build()?
+            }
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::Time32(_)
+            | DataType::Date32
+            | DataType::Interval(IntervalUnit::YearMonth)
+            | DataType::Float32 => {
+                let (length, nulls) = self.node(field)?;
+                let null_buffer = self.null_buffer(nulls)?;
+                let (offset, size) = self.next_buffer()?;
+                // Like arrow, read values of 64 bits as written by some
+                // implementations, and cast them.
+                if size / 8 == length && length != 1 {
+                    let wide_type = match data_type {
+                        DataType::Float32 => DataType::Float64,
+                        _ => DataType::Int64,
+                    };
+                    let wide = ArrayData::builder(wide_type)
+                        .len(length)
+                        .null_bit_buffer(null_buffer)
+                        .add_buffer(self.slice(offset, size, 8))
+                        .build()?;
+                    cast(&make_array(wide), data_type)?.data().clone()
+                } else {
+                    let width = primitive_width(data_type);
+                    if strict && size % width != 0 {
+                        return Err(self.partial_values(size, width));
+                    }
+                    ArrayData::builder(data_type.clone())
+                        .len(length)
+                        .null_bit_buffer(null_buffer)
+                        .add_buffer(self.slice(offset, size, width))
+                        .build()?
+                }
+            }
+            DataType::Int64
+            | DataType::UInt64
+            | DataType::Float64
+            | DataType::Time64(_)
+            | DataType::Timestamp(_, _)
+            | DataType::Date64
+            | DataType::Duration(_)
+            | DataType::Interval(_) => {
+                let (length, nulls) = self.node(field)?;
+                let null_buffer = self.null_buffer(nulls)?;
+                let width = primitive_width(data_type);
+                // Arrow reads the values in place, which requires month, day
+                // and nanosecond intervals to be aligned as 128 bit values.
+                let alignment = match data_type {
+                    DataType::Interval(IntervalUnit::MonthDayNano) => I128_ALIGNMENT,
+                    _ => width,
+                };
+                let values = match strict {
+                    true => self.typed_buffer(width, alignment)?,
+                    false => self.buffer(alignment)?,
+                };
+                ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .null_bit_buffer(null_buffer)
+                    .add_buffer(values)
+                    .build()?
+            }
+            DataType::List(child) | DataType::LargeList(child) | DataType::Map(child, _) => {
+                let (length, nulls) = self.node(field)?;
+                let null_buffer = self.null_buffer(nulls)?;
+                let offset_width = match data_type {
+                    DataType::LargeList(_) => 8,
+                    _ => 4,
+                };
+                let offsets = self.typed_buffer(offset_width, offset_width)?;
+                let child = self.array(child, true)?;
+                ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .null_bit_buffer(null_buffer)
+                    .add_buffer(offsets)
+                    .add_child_data(child)
+                    .build()?
+            }
+            DataType::FixedSizeList(child, _) => {
+                let (length, nulls) = self.node(field)?;
+                let null_buffer = self.null_buffer(nulls)?;
+                let child = self.array(child, true)?;
+                ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .null_bit_buffer(null_buffer)
+                    .add_child_data(child)
+                    .build()?
+            }
+            DataType::Struct(fields) => {
+                let (_, nulls) = self.node(field)?;
+                let null_buffer = self.null_buffer(nulls)?;
+                let children = fields
+                    .iter()
+                    .map(|child| self.array(child, strict))
+                    .collect::<ArrowResult<Vec<_>>>()?;
+                // Arrow takes the length of a struct from its first child.
+                let length = children
+                    .first()
+                    .ok_or_else(|| invalid(format!("Struct {} has no fields", field.name())))?
+                    .len();
+                if let Some(child) = children.iter().find(|child| child.len() != length) {
+                    return Err(invalid(format!(
+                        "The fields of struct {} have {} and {} values",
+                        field.name(),
+                        length,
+                        child.len()
+                    )));
+                }
+                ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .null_bit_buffer(null_buffer)
+                    .child_data(children)
+                    .build()?
+            }
+            DataType::Dictionary(key_type, value_type) => {
+                let (length, nulls) = self.node(field)?;
+                let null_buffer = self.null_buffer(nulls)?;
+                let key_width = primitive_width(key_type);
+                let keys = ArrayData::builder(key_type.as_ref().clone())
+                    .len(length)
+                    .null_bit_buffer(null_buffer)
+                    .add_buffer(self.typed_buffer(key_width, key_width)?)
+                    .build()?;
+                let dict_id = field
+                    .dict_id()
+                    .ok_or_else(|| invalid(format!("Field {} has no dictionary id", field)))?;
+                let values = self.dictionaries_by_id.get(&dict_id).ok_or_else(|| {
+                    invalid(format!(
+                        "No dictionary with id {} was sent before the record batch",
+                        dict_id
+                    ))
+                })?;
+                if values.data_type() != value_type.as_ref() {
+                    return Err(invalid(format!(
+                        "Dictionary {} holds values of type {}, field {} of type {}",
+                        dict_id,
+                        values.data_type(),
+                        field.name(),
+                        value_type
+                    )));
+                }
+                // Only the keys are checked, the values were checked when the
+                // dictionary was read.
+                dictionary_array(key_type, make_array(keys), values.as_ref())?
+            }
+            DataType::Union(fields, type_ids, mode) => {
+                let (length, _) = self.node(field)?;
+                // Unions have a validity buffer before version 5, which arrow
+                // ignores.
+                if self.metadata < ipc::MetadataVersion::V5 {
+                    self.buffer(1)?;
+                }
+                let union_type_ids = self.buffer(1)?;
+                let union_type_ids = union_type_ids.as_slice().get(..length).ok_or_else(|| {
+                    invalid(format!(
+                        "Union {} has {} type ids for {} values",
+                        field.name(),
+                        union_type_ids.len(),
+                        length
+                    ))
+                })?;
+                let union_type_ids = Buffer::from(union_type_ids);
+                let value_offsets = match mode {
+                    UnionMode::Dense => {
+                        let value_offsets = self.buffer(4)?;
+                        let value_offsets =
+                            value_offsets.as_slice().get(..length * 4).ok_or_else(|| {
+                                invalid(format!(
+                                    "Union {} has {} bytes of offsets for {} values",
+                                    field.name(),
+                                    value_offsets.len(),
+                                    length
+                                ))
+                            })?;
+                        Some(Buffer::from(value_offsets))
+                    }
+                    UnionMode::Sparse => None,
+                };
+                let children = fields
+                    .iter()
+                    .map(|child| self.array(child, strict))
+                    .collect::<ArrowResult<Vec<_>>>()?;
+
+                // Arrow finds the child of a value by using its type id as
+                // the index of the child.
+                for (index, type_id) in union_type_ids.iter().enumerate() {
+                    let type_id = *type_id as i8;
+                    let child = usize::try_from(type_id)
+                        .ok()
+                        .and_then(|child| children.get(child))
+                        .filter(|_| type_ids.contains(&type_id))
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "Value {} of union {} has the unknown type id {}",
+                                index,
+                                field.name(),
+                                type_id
+                            ))
+                        })?;
+                    if let Some(value_offsets) = &value_offsets {
+                        let value_offset = &value_offsets.as_slice()[index * 4..index * 4 + 4];
+                        let value_offset = i32::from_le_bytes(value_offset.try_into().unwrap());
+                        if usize::try_from(value_offset)
+                            .ok()
+                            .is_none_or(|offset| offset >= child.len())
+                        {
+                            return Err(invalid(format!(
+                                "Value {} of union {} has the offset {} in a child of {} values",
+                                index,
+                                field.name(),
+                                value_offset,
+                                child.len()
+                            )));
+                        }
+                    }
+                }
+
+                let builder = ArrayData::builder(data_type.clone())
+                    .len(length)
+                    .add_buffer(union_type_ids)
+                    .child_data(children);
+                match value_offsets {
+                    Some(value_offsets) => builder.add_buffer(value_offsets).build()?,
+                    None => builder.build()?,
+                }
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "Field {} has the unsupported type {}",
+                    field.name(),
+                    data_type
+                )))
+            }
+        };
+        Ok(array)
+    }
+
+    /// The length and the null count of the next field node.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn node(&mut self, field: &Field) -> ArrowResult<(usize, usize)> {
+        let node = self.nodes.get(self.node_index).ok_or_else(|| {
+            invalid(format!(
+                "The record batch has {} field nodes, none for field {}",
+                self.nodes.len(),
+                field.name()
+            ))
+        })?;
+        self.node_index += 1;
+        let length = checked_length(node.length(), field.name())?;
+        let null_count = checked_length(node.null_count(), field.name())?;
+        if null_count > length {
+            return Err(invalid(format!(
+                "Field {} has {} nulls in {} values",
+                field.name(),
+                null_count,
+                length
+            )));
+        }
+        Ok((length, null_count))
+    }
+
+    /// The next buffer, used as the validity of an array like arrow does,
+    /// only when the array has nulls.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn null_buffer(&mut self, null_count: usize) -> ArrowResult<Option<Buffer>> {
+        let buffer = self.buffer(1)?;
+        Ok((null_count > 0).then_some(buffer))
+    }
+
+    /// The offset and length of the next buffer, which must be within the
+    /// bounds of the body.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn next_buffer(&mut self) -> ArrowResult<(usize, usize)> {
+        let index = self.buffer_index;
+        let buffer = self.buffers.get(index).ok_or_else(|| {
+            invalid(format!(
+                "The record batch has {} buffers, its schema more",
+                self.buffers.len()
+            ))
+        })?;
+        self.buffer_index += 1;
+        let (offset, length) = (buffer.offset(), buffer.length());
+        let in_bounds = offset >= 0
+            && length >= 0
+            && offset
+                .checked_add(length)
+                .is_some_and(|end| end as u64 <= self.body.len() as u64);
+        if !in_bounds {
+            return Err(invalid(format!(
+                "Buffer {} of {} bytes at offset {} is out of the bounds of the body of {} bytes",
+                index,
+                length,
+                offset,
+                self.body.len()
+            )));
+        }
+        Ok((offset as usize, length as usize))
+    }
+
+    /// The bytes of the body at the offset, copied when they do not start at
+    /// a multiple of `alignment` in memory, as arrow reads values in place.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn slice(&self, offset: usize, length: usize, alignment: usize) -> Buffer {
+        let buffer = self.body.slice_with_length(offset, length);
+        match buffer.as_ptr().align_offset(alignment) {
+            0 => buffer,
+            _ => Buffer::from(buffer.as_slice()),
+        }
+    }
+
+    /// The next buffer, aligned to `alignment`.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn buffer(&mut self, alignment: usize) -> ArrowResult<Buffer> {
+        let (offset, length) = self.next_buffer()?;
+        Ok(self.slice(offset, length, alignment))
+    }
+
+    /// The next buffer, which arrow reads as values of `width` bytes, so it
+    /// must hold whole values.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn typed_buffer(&mut self, width: usize, alignment: usize) -> ArrowResult<Buffer> {
+        let (offset, length) = self.next_buffer()?;
+        if length % width != 0 {
+            return Err(self.partial_values(length, width));
+        }
+        Ok(self.slice(offset, length, alignment))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn partial_values(&self, length: usize, width: usize) -> ArrowError {
+        invalid(format!(
+            "Buffer {} of {} bytes does not hold whole values of {} bytes",
+            self.buffer_index - 1,
+            length,
+            width
+        ))
+    }
+}
+
+/// A dictionary array of the keys and values, which checks that the keys
+/// are within the values without checking the values again.
+#[tracing::instrument(level = "debug", skip_all)]
+fn dictionary_array(
+    key_type: &DataType,
+    keys: ArrayRef,
+    values: &dyn Array,
+) -> ArrowResult<ArrayData> {
+    macro_rules! dictionary {
+        ($key_type:ty) => {
+            DictionaryArray::<$key_type>::try_new(as_primitive_array(&keys), values)?
+                .data()
+                .clone()
+        };
+    }
+    Ok(match key_type {
+        DataType::Int8 => dictionary!(Int8Type),
+        DataType::Int16 => dictionary!(Int16Type),
+        DataType::Int32 => dictionary!(Int32Type),
+        DataType::Int64 => dictionary!(Int64Type),
+        DataType::UInt8 => dictionary!(UInt8Type),
+        DataType::UInt16 => dictionary!(UInt16Type),
+        DataType::UInt32 => dictionary!(UInt32Type),
+        DataType::UInt64 => dictionary!(UInt64Type),
+        _ => return Err(invalid(format!("Invalid dictionary key type {}", key_type))),
+    })
+}
+
+/// A length sent by the server, which must be positive and at most
+/// `MAX_LENGTH`.
+#[tracing::instrument(level = "debug", skip_all)]
+fn checked_length(length: i64, what: &str) -> ArrowResult<usize> {
+    if (0..=MAX_LENGTH).contains(&length) {
+        Ok(length as usize)
+    } else {
+        Err(invalid(format!("Invalid length {} of {}", length, what)))
+    }
+}
+
+/// The size of the values of a fixed width type.
+#[tracing::instrument(level = "debug", skip_all)]
+fn primitive_width(data_type: &DataType) -> usize {
+    match data_type {
+        DataType::Int8 | DataType::UInt8 => 1,
+        DataType::Int16 | DataType::UInt16 => 2,
+        DataType::Int32
+        | DataType::UInt32
+        | DataType::Float32
+        | DataType::Time32(_)
+        | DataType::Date32
+        | DataType::Interval(IntervalUnit::YearMonth) => 4,
+        DataType::Interval(IntervalUnit::MonthDayNano) => 16,
+        _ => 8,
+    }
+}
@@ -0,0 +1,40 @@
+//! Tests of the authentication with the Handshake RPC.
+
+mod common;
+
+use arrow_flight_sql_client::connection::ClientBuilder;
+use common::{Config, TestServer, PASSWORD, TOKEN, USERNAME};
+use tonic::Code;
+
+#[tokio::test]
+async fn token_of_the_handshake_is_sent_with_later_calls() {
+    let server = TestServer::start(Config::default()).await;
+    let client = ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .with_basic_auth(USERNAME, PASSWORD)
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(client.token().as_deref(), Some(TOKEN));
+    client.get_catalogs().await.unwrap();
+
+    let calls = server.calls();
+    assert_eq!(calls[0].method, "Handshake");
+    assert_eq!(calls[1].method, "GetFlightInfo");
+    let authorization = calls[1].metadata.get("authorization").unwrap();
+    assert_eq!(authorization, format!("Bearer {}", TOKEN).as_str());
+}
+
+#[tokio::test]
+async fn rejected_handshake_is_an_authentication_error() {
+    let server = TestServer::start(Config::default()).await;
+    let err = ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .with_basic_auth(USERNAME, "wrong")
+        .connect()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(Code::Unauthenticated));
+    assert_eq!(server.count("Handshake"), 1);
+    assert_eq!(server.calls().len(), 1);
+}
@@ -0,0 +1,487 @@
+//! A Flight SQL server for the tests of the client, which answers every
+//! query with the same batches and records the requests it receives.
+
+#![allow(dead_code, clippy::result_large_err)]
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use arrow::{
+    array::{ArrayRef, Int64Array},
+    datatypes::{DataType, Field, Schema},
+    ipc::writer::{IpcDataGenerator, IpcWriteOptions},
+    record_batch::RecordBatch,
+};
+use arrow_flight_sql_client::{
+    arrow_flight_protocol::{
+        flight_service_server::{FlightService, FlightServiceServer},
+        Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint,
+        FlightInfo, HandshakeRequest, HandshakeResponse, Location, PutResult, SchemaResult, Ticket,
+    },
+    arrow_flight_protocol_sql::{ActionCreatePreparedStatementResult, DoPutUpdateResult},
+    client::{flight_data_from_arrow_batches, ArrowFlightData, FlightDataDecoder, ProstMessageExt},
+};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use prost::Message;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{
+    metadata::MetadataMap,
+    transport::{Identity, Server, ServerTlsConfig},
+    Code, Request, Response, Status, Streaming,
+};
+
+/// The credentials accepted by the Handshake of the test server.
+pub const USERNAME: &str = "analyst";
+pub const PASSWORD: &str = "secret";
+/// The bearer token returned by the Handshake of the test server.
+pub const TOKEN: &str = "test-token";
+
+/// How the test server answers.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The number of calls that fail before the server answers.
+    pub failures: usize,
+    /// The method whose calls fail, or None for calls of any method.
+    pub failing_method: Option<&'static str>,
+    /// The code of the failed calls.
+    pub failure_code: Code,
+    /// The locations of the endpoints returned by GetFlightInfo.
+    pub locations: Vec<String>,
+    /// The number of batches of each DoGet stream.
+    pub batches: usize,
+    /// The time waited before each batch.
+    pub batch_delay: Duration,
+    /// Stop sending batches after the first one, without ending the stream.
+    pub stall: bool,
+    /// The parameter schema of the prepared statements, or None for
+    /// statements without parameters.
+    pub parameter_schema: Option<Schema>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            failures: 0,
+            failing_method: None,
+            failure_code: Code::Unavailable,
+            locations: vec![],
+            batches: 2,
+            batch_delay: Duration::ZERO,
+            stall: false,
+            parameter_schema: None,
+        }
+    }
+}
+
+/// A request received by the test server.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub method: &'static str,
+    pub metadata: MetadataMap,
+}
+
+/// The messages of a DoPut request received by the test server.
+#[derive(Debug, Clone)]
+pub struct Put {
+    pub descriptor: Option<FlightDescriptor>,
+    pub schema: Option<Schema>,
+    pub batches: Vec<RecordBatch>,
+}
+
+/// A running test server.
+pub struct TestServer {
+    pub addr: SocketAddr,
+    calls: Arc<Mutex<Vec<Call>>>,
+    puts: Arc<Mutex<Vec<Put>>>,
+}
+
+impl TestServer {
+    /// Start a server on a free port of the loopback interface.
+    pub async fn start(config: Config) -> TestServer {
+        Self::start_with(config, None).await
+    }
+
+    /// Start a server that accepts TLS connections.
+    pub async fn start_tls(config: Config, tls: ServerTlsConfig) -> TestServer {
+        Self::start_with(config, Some(tls)).await
+    }
+
+    async fn start_with(config: Config, tls: Option<ServerTlsConfig>) -> TestServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let calls = Arc::new(Mutex::new(vec![]));
+        let puts = Arc::new(Mutex::new(vec![]));
+        let service = TestService {
+            config,
+            calls: calls.clone(),
+            puts: puts.clone(),
+        };
+        let mut server = Server::builder();
+        if let Some(tls) = tls {
+            server = server.tls_config(tls).unwrap();
+        }
+        let router = server.add_service(FlightServiceServer::new(service));
+        tokio::spawn(router.serve_with_incoming(TcpListenerStream::new(listener)));
+        TestServer { addr, calls, puts }
+    }
+
+    /// The uri of the server, for plain TCP connections.
+    pub fn uri(&self) -> String {
+        format!("grpc+tcp://{}", self.addr)
+    }
+
+    /// The uri of the server, for TLS connections verified with the
+    /// certificates of `TestCertificates`.
+    pub fn tls_uri(&self) -> String {
+        format!("grpc+tls://localhost:{}", self.addr.port())
+    }
+
+    /// The requests received so far.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The DoPut requests received so far, in order.
+    pub fn puts(&self) -> Vec<Put> {
+        self.puts.lock().unwrap().clone()
+    }
+
+    /// The number of requests received so far for the method.
+    pub fn count(&self, method: &str) -> usize {
+        self.calls()
+            .iter()
+            .filter(|call| call.method == method)
+            .count()
+    }
+}
+
+/// A certificate authority, with a certificate it signed for `localhost`
+/// and one for a client.
+pub struct TestCertificates {
+    pub ca: Vec<u8>,
+    pub server_certificate: Vec<u8>,
+    pub server_key: Vec<u8>,
+    pub client_certificate: Vec<u8>,
+    pub client_key: Vec<u8>,
+}
+
+impl TestCertificates {
+    pub fn generate() -> TestCertificates {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = Certificate::from_params(params).unwrap();
+        let server =
+            Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()]))
+                .unwrap();
+        let client =
+            Certificate::from_params(CertificateParams::new(vec!["client".to_string()])).unwrap();
+        TestCertificates {
+            ca: ca.serialize_pem().unwrap().into_bytes(),
+            server_certificate: server.serialize_pem_with_signer(&ca).unwrap().into_bytes(),
+            server_key: server.serialize_private_key_pem().into_bytes(),
+            client_certificate: client.serialize_pem_with_signer(&ca).unwrap().into_bytes(),
+            client_key: client.serialize_private_key_pem().into_bytes(),
+        }
+    }
+
+    /// The TLS settings of a server presenting the `localhost` certificate.
+    pub fn server_tls(&self) -> ServerTlsConfig {
+        ServerTlsConfig::new().identity(Identity::from_pem(
+            &self.server_certificate,
+            &self.server_key,
+        ))
+    }
+
+    /// The TLS settings of a server that also requires a client certificate
+    /// signed by the authority.
+    pub fn server_mtls(&self) -> ServerTlsConfig {
+        self.server_tls()
+            .client_ca_root(tonic::transport::Certificate::from_pem(&self.ca))
+    }
+}
+
+/// The schema of the batches returned by DoGet.
+pub fn test_schema() -> Schema {
+    Schema::new(vec![Field::new("value", DataType::Int64, false)])
+}
+
+pub fn test_batch(value: i64) -> RecordBatch {
+    RecordBatch::try_new(
+        Arc::new(test_schema()),
+        vec![Arc::new(Int64Array::from(vec![value; 3])) as ArrayRef],
+    )
+    .unwrap()
+}
+
+/// The schema as an encapsulated IPC message.
+fn schema_bytes(schema: &Schema) -> Vec<u8> {
+    let message = IpcDataGenerator::default()
+        .schema_to_bytes(schema, &IpcWriteOptions::default())
+        .ipc_message;
+    let mut bytes = vec![0xff; 4];
+    bytes.extend_from_slice(&(message.len() as i32).to_le_bytes());
+    bytes.extend_from_slice(&message);
+    bytes
+}
+
+/// The `grpc-timeout` deadline of a request.
+fn deadline(metadata: &MetadataMap) -> Option<Duration> {
+    let timeout = metadata.get("grpc-timeout")?.to_str().ok()?;
+    let (value, unit) = timeout.split_at(timeout.len().checked_sub(1)?);
+    let value = value.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(value * 3600),
+        "M" => Duration::from_secs(value * 60),
+        "S" => Duration::from_secs(value),
+        "m" => Duration::from_millis(value),
+        "u" => Duration::from_micros(value),
+        "n" => Duration::from_nanos(value),
+        _ => return None,
+    })
+}
+
+struct TestService {
+    config: Config,
+    calls: Arc<Mutex<Vec<Call>>>,
+    puts: Arc<Mutex<Vec<Put>>>,
+}
+
+impl TestService {
+    /// Record the request, and fail it while the configured number of
+    /// failures of its method is not reached.
+    fn record(&self, method: &'static str, metadata: &MetadataMap) -> Result<(), Status> {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(Call {
+            method,
+            metadata: metadata.clone(),
+        });
+        let failing = |call: &Call| {
+            self.config
+                .failing_method
+                .is_none_or(|failing| failing == call.method)
+        };
+        if !failing(calls.last().unwrap()) {
+            return Ok(());
+        }
+        let failed = calls.iter().filter(|call| failing(call)).count();
+        if failed <= self.config.failures {
+            return Err(Status::new(
+                self.config.failure_code,
+                format!("failure {} of {}", failed, self.config.failures),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for TestService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoActionStream =
+        BoxStream<'static, Result<arrow_flight_sql_client::arrow_flight_protocol::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+
+    async fn handshake(
+        &self,
+        request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        self.record("Handshake", request.metadata())?;
+        let expected = format!(
+            "Basic {}",
+            base64::encode(format!("{}:{}", USERNAME, PASSWORD))
+        );
+        let authorization = request.metadata().get("authorization");
+        if authorization.and_then(|value| value.to_str().ok()) != Some(expected.as_str()) {
+            return Err(Status::unauthenticated("Invalid username or password"));
+        }
+        let mut response = Response::new(
+            stream::iter(vec![Ok(HandshakeResponse {
+                protocol_version: 0,
+                payload: TOKEN.as_bytes().to_vec(),
+            })])
+            .boxed(),
+        );
+        response.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", TOKEN).parse().unwrap(),
+        );
+        Ok(response)
+    }
+
+    async fn list_flights(
+        &self,
+        request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        self.record("ListFlights", request.metadata())?;
+        Err(Status::unimplemented("ListFlights"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        self.record("GetFlightInfo", request.metadata())?;
+        let endpoint = FlightEndpoint {
+            ticket: Some(Ticket {
+                ticket: b"ticket".to_vec(),
+            }),
+            location: self
+                .config
+                .locations
+                .iter()
+                .map(|uri| Location { uri: uri.clone() })
+                .collect(),
+        };
+        Ok(Response::new(FlightInfo {
+            schema: schema_bytes(&test_schema()),
+            flight_descriptor: Some(request.into_inner()),
+            endpoint: vec![endpoint],
+            total_records: -1,
+            total_bytes: -1,
+        }))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        self.record("GetSchema", request.metadata())?;
+        Err(Status::unimplemented("GetSchema"))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        self.record("DoGet", request.metadata())?;
+        let batches = (0..self.config.batches as i64)
+            .map(test_batch)
+            .collect::<Vec<_>>();
+        let messages =
+            flight_data_from_arrow_batches(FlightDescriptor::new_cmd(vec![]), &batches).unwrap();
+        let delay = self.config.batch_delay;
+        // The first message is the schema, which is sent right away.
+        let stalled = self.config.stall.then(|| messages.len().min(2));
+        // Like most servers, end the whole stream at the deadline of the call.
+        let deadline =
+            deadline(request.metadata()).map(|timeout| tokio::time::Instant::now() + timeout);
+        let stream = stream::iter(messages.into_iter().enumerate())
+            .then(move |(index, message)| async move {
+                let message = async move {
+                    if stalled.is_some_and(|stalled| index >= stalled) {
+                        futures::future::pending::<()>().await;
+                    }
+                    if index > 0 {
+                        tokio::time::sleep(delay).await;
+                    }
+                    message
+                };
+                match deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline, message)
+                        .await
+                        .map_err(|_| Status::deadline_exceeded("Deadline of DoGet exceeded")),
+                    None => Ok(message.await),
+                }
+            })
+            .boxed();
+        Ok(Response::new(stream))
+    }
+
+    async fn do_put(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        self.record("DoPut", request.metadata())?;
+        let mut messages = request.into_inner();
+        let mut put = Put {
+            descriptor: None,
+            schema: None,
+            batches: vec![],
+        };
+        let mut decoder = FlightDataDecoder::new(Arc::new(Schema::empty()));
+        while let Some(mut message) = messages.message().await? {
+            if let Some(descriptor) = message.flight_descriptor.take() {
+                put.descriptor = Some(descriptor);
+            }
+            if message.data_header.is_empty() {
+                continue;
+            }
+            match decoder.decode(message) {
+                Ok(ArrowFlightData::Schema(schema)) => put.schema = Some(schema),
+                Ok(ArrowFlightData::RecordBatch(batch)) => put.batches.push(batch),
+                Ok(ArrowFlightData::Dictionary(_)) => {}
+                Err(err) => return Err(Status::invalid_argument(err.to_string())),
+            }
+        }
+        // Like an update executed once per parameter row, or once without
+        // parameters.
+        let record_count = match put.batches.is_empty() {
+            true => 1,
+            false => put.batches.iter().map(RecordBatch::num_rows).sum::<usize>() as i64,
+        };
+        self.puts.lock().unwrap().push(put);
+        let result = DoPutUpdateResult { record_count };
+        Ok(Response::new(
+            stream::iter(vec![Ok(PutResult {
+                app_metadata: result.as_any().encode_to_vec(),
+            })])
+            .boxed(),
+        ))
+    }
+
+    async fn do_exchange(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        self.record("DoExchange", request.metadata())?;
+        Err(Status::unimplemented("DoExchange"))
+    }
+
+    async fn do_action(
+        &self,
+        request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        self.record("DoAction", request.metadata())?;
+        let body = match request.get_ref().r#type.as_str() {
+            "CreatePreparedStatement" => ActionCreatePreparedStatementResult {
+                prepared_statement_handle: b"handle".to_vec(),
+                dataset_schema: schema_bytes(&test_schema()),
+                parameter_schema: self
+                    .config
+                    .parameter_schema
+                    .as_ref()
+                    .map(schema_bytes)
+                    .unwrap_or_default(),
+            }
+            .as_any()
+            .encode_to_vec(),
+            _ => vec![],
+        };
+        Ok(Response::new(
+            stream::iter(vec![Ok(
+                arrow_flight_sql_client::arrow_flight_protocol::Result { body },
+            )])
+            .boxed(),
+        ))
+    }
+
+    async fn list_actions(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        self.record("ListActions", request.metadata())?;
+        Err(Status::unimplemented("ListActions"))
+    }
+}
@@ -0,0 +1,43 @@
+//! Tests of clients and prepared statements shared between tasks.
+
+mod common;
+
+use arrow_flight_sql_client::connection::ClientBuilder;
+use common::{test_batch, Config, TestServer};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn clones_run_queries_concurrently() {
+    let server = TestServer::start(Config::default()).await;
+    let client = ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let statement = client.prepare("select 1".to_string()).await.unwrap();
+
+    let tasks = (0..2)
+        .map(|_| {
+            let client = client.clone();
+            let statement = statement.clone();
+            tokio::spawn(async move {
+                let fi = client.execute("select 1".to_string()).await.unwrap();
+                let batches = client.do_get_batches(&fi).await.unwrap();
+                let fi = statement.execute().await.unwrap();
+                (batches, client.do_get_batches(&fi).await.unwrap())
+            })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        let (batches, prepared_batches) = task.await.unwrap();
+        assert_eq!(batches, vec![test_batch(0), test_batch(1)]);
+        assert_eq!(prepared_batches, batches);
+    }
+
+    assert_eq!(server.count("DoAction"), 1);
+    assert_eq!(server.count("GetFlightInfo"), 4);
+    assert_eq!(server.count("DoGet"), 4);
+    // The clones share the stats of the client.
+    let stats = client.stats();
+    assert_eq!(stats.get_flight_info_calls, 4);
+    assert_eq!(stats.do_get_calls, 4);
+}
@@ -0,0 +1,149 @@
+//! Tests of the connections of the client to a local server.
+
+mod common;
+
+use arrow::record_batch::RecordBatch;
+use arrow_flight_sql_client::{
+    connection::{ClientBuilder, TlsOptions},
+    error::{FlightSqlError, Result},
+};
+use common::{test_batch, Config, TestCertificates, TestServer};
+
+#[tokio::test]
+async fn location_connection_inherits_the_settings() {
+    let certificates = TestCertificates::generate();
+    let location = TestServer::start_tls(Config::default(), certificates.server_tls()).await;
+    let server = TestServer::start_tls(
+        Config {
+            locations: vec![format!("{}?header.x-node=2", location.tls_uri())],
+            ..Default::default()
+        },
+        certificates.server_tls(),
+    )
+    .await;
+
+    // The location is only trusted with the CA of the client.
+    let client = ClientBuilder::from_uri(&format!(
+        "{}?header.x-tenant=acme&timeout=30",
+        server.tls_uri()
+    ))
+    .unwrap()
+    .with_tls(TlsOptions {
+        ca_certificate: Some(certificates.ca.clone()),
+        ..Default::default()
+    })
+    .connect()
+    .await
+    .unwrap();
+    let fi = client.execute("select 1".to_string()).await.unwrap();
+    let batches = client.do_get_batches(&fi).await.unwrap();
+    assert_eq!(batches, vec![test_batch(0), test_batch(1)]);
+
+    assert_eq!(server.count("DoGet"), 0);
+    let calls = location.calls();
+    assert_eq!(calls.len(), 1);
+    let metadata = &calls[0].metadata;
+    assert_eq!(metadata.get("x-tenant").unwrap(), "acme");
+    assert_eq!(metadata.get("x-node").unwrap(), "2");
+    // The deadline of the client is not sent with the DoGet stream.
+    assert!(metadata.get("grpc-timeout").is_none());
+
+    // The headers of the location are not sent to the server.
+    client.execute("select 1".to_string()).await.unwrap();
+    assert!(server
+        .calls()
+        .iter()
+        .all(|call| call.metadata.get("x-node").is_none()));
+}
+
+/// Run a query with the client of the builder.
+async fn query(builder: ClientBuilder) -> Result<Vec<RecordBatch>> {
+    let client = builder.connect().await?;
+    let fi = client.execute("select 1".to_string()).await?;
+    client.do_get_batches(&fi).await
+}
+
+#[tokio::test]
+async fn tls_with_custom_ca() {
+    let certificates = TestCertificates::generate();
+    let server = TestServer::start_tls(Config::default(), certificates.server_tls()).await;
+    let builder = ClientBuilder::from_uri(&server.tls_uri())
+        .unwrap()
+        .with_tls(TlsOptions {
+            ca_certificate: Some(certificates.ca.clone()),
+            ..Default::default()
+        });
+    assert_eq!(query(builder).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn tls_rejects_unknown_ca() {
+    let certificates = TestCertificates::generate();
+    let server = TestServer::start_tls(Config::default(), certificates.server_tls()).await;
+    let builder = ClientBuilder::from_uri(&server.tls_uri()).unwrap();
+    match query(builder).await {
+        Err(FlightSqlError::Transport(_)) => {}
+        other => panic!("Expected a transport error, found {:?}", other),
+    }
+    assert!(server.calls().is_empty());
+}
+
+#[tokio::test]
+async fn mutual_tls_with_client_identity() {
+    let certificates = TestCertificates::generate();
+    let server = TestServer::start_tls(Config::default(), certificates.server_mtls()).await;
+    let tls = TlsOptions {
+        ca_certificate: Some(certificates.ca.clone()),
+        ..Default::default()
+    };
+
+    let builder = ClientBuilder::from_uri(&server.tls_uri())
+        .unwrap()
+        .with_tls(TlsOptions {
+            client_identity: Some((
+                certificates.client_certificate.clone(),
+                certificates.client_key.clone(),
+            )),
+            ..tls.clone()
+        });
+    assert_eq!(query(builder).await.unwrap().len(), 2);
+
+    let builder = ClientBuilder::from_uri(&server.tls_uri())
+        .unwrap()
+        .with_tls(tls);
+    assert!(query(builder).await.is_err());
+}
+
+#[tokio::test]
+async fn tls_insecure_skip_verify() {
+    let certificates = TestCertificates::generate();
+    let server = TestServer::start_tls(Config::default(), certificates.server_mtls()).await;
+    let builder = ClientBuilder::from_uri(&server.tls_uri())
+        .unwrap()
+        .with_tls(TlsOptions {
+            client_identity: Some((
+                certificates.client_certificate.clone(),
+                certificates.client_key.clone(),
+            )),
+            insecure_skip_verify: true,
+            ..Default::default()
+        });
+    assert_eq!(query(builder).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn tls_insecure_skip_verify_conflicts_with_ca() {
+    let certificates = TestCertificates::generate();
+    let server = TestServer::start_tls(Config::default(), certificates.server_tls()).await;
+    let builder = ClientBuilder::from_uri(&server.tls_uri())
+        .unwrap()
+        .with_tls(TlsOptions {
+            ca_certificate: Some(certificates.ca.clone()),
+            insecure_skip_verify: true,
+            ..Default::default()
+        });
+    match query(builder).await {
+        Err(FlightSqlError::InvalidArgument(_)) => {}
+        other => panic!("Expected an invalid argument, found {:?}", other),
+    }
+}
@@ -0,0 +1,623 @@
+//! Property tests for the helpers that decode the data returned by a server.
+//!
+//! A buggy or hostile server can send anything, so the helpers must return an
+//! error for arbitrary and corrupted input instead of panicking.
+
+use std::{
+    cell::Cell,
+    sync::{Arc, Once},
+};
+
+use arrow::{
+    array::{
+        Array, ArrayRef, BooleanArray, Date32Array, Decimal128Array, DictionaryArray,
+        FixedSizeBinaryArray, Float32Array, Int32Array, Int8Array, LargeListArray,
+        LargeStringArray, ListArray, StringArray, StructArray, TimestampMicrosecondArray,
+        UInt32Array, UnionBuilder,
+    },
+    compute::cast,
+    datatypes::{DataType, Field, Float64Type, Int32Type, Int64Type, Schema, SchemaRef},
+    ipc::{
+        self,
+        writer::{DictionaryTracker, IpcDataGenerator, IpcWriteOptions},
+    },
+    record_batch::RecordBatch,
+};
+use arrow_flight_sql_client::{
+    arrow_flight_protocol::{FlightData, FlightDescriptor, FlightInfo},
+    arrow_flight_protocol_sql::{ActionCreatePreparedStatementResult, DoPutUpdateResult},
+    client::{
+        arrow_schema_from_flight_info, decode_any, flight_data_from_arrow_batches, ArrowFlightData,
+        FlightDataDecoder, IpcMessage, ProstMessageExt,
+    },
+    error::FlightSqlError,
+    sql_info::decode_sql_info,
+};
+use proptest::prelude::*;
+use prost::Message;
+
+fn test_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("id", DataType::Int32, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new_dict(
+            "category",
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+            true,
+            0,
+            false,
+        ),
+    ])
+}
+
+fn test_batch() -> RecordBatch {
+    let categories: DictionaryArray<Int32Type> = vec!["a", "b", "a", "c"].into_iter().collect();
+    RecordBatch::try_new(
+        Arc::new(test_schema()),
+        vec![
+            Arc::new(Int32Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+            Arc::new(StringArray::from(vec![
+                Some("x"),
+                None,
+                Some("y"),
+                Some("z"),
+            ])),
+            Arc::new(categories),
+        ],
+    )
+    .unwrap()
+}
+
+/// A batch in the layout returned by GetSqlInfo.
+fn sql_info_batch() -> RecordBatch {
+    let mut values = UnionBuilder::new_dense();
+    values.append::<Int64Type>("bigint", 42).unwrap();
+    values.append::<Int32Type>("int32_bitmask", 7).unwrap();
+    values.append::<Int64Type>("bigint", -1).unwrap();
+    let values = values.build().unwrap();
+    let schema = Schema::new(vec![
+        Field::new("info_name", DataType::UInt32, false),
+        Field::new("value", values.data_type().clone(), false),
+    ]);
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(UInt32Array::from(vec![1, 2, 3])) as ArrayRef,
+            Arc::new(values),
+        ],
+    )
+    .unwrap()
+}
+
+/// A batch with columns of most of the types arrow reads.
+fn nested_batch() -> RecordBatch {
+    let mut values = UnionBuilder::new_sparse();
+    values.append::<Int32Type>("int", 1).unwrap();
+    values.append::<Float64Type>("float", 2.5).unwrap();
+    values.append_null::<Int32Type>("int").unwrap();
+    let values = values.build().unwrap();
+    let point = StructArray::from(vec![
+        (
+            Field::new("x", DataType::Int8, true),
+            Arc::new(Int8Array::from(vec![Some(1), None, Some(3)])) as ArrayRef,
+        ),
+        (
+            Field::new("label", DataType::LargeUtf8, true),
+            Arc::new(LargeStringArray::from(vec![Some("a"), Some("b"), None])),
+        ),
+    ]);
+    let columns: Vec<(&str, ArrayRef)> = vec![
+        (
+            "flag",
+            Arc::new(BooleanArray::from(vec![Some(true), None, Some(false)])),
+        ),
+        ("ratio", Arc::new(Float32Array::from(vec![0.5, 1.5, -2.0]))),
+        (
+            "day",
+            Arc::new(Date32Array::from(vec![Some(19000), None, Some(0)])),
+        ),
+        (
+            "time",
+            Arc::new(TimestampMicrosecondArray::from(vec![
+                Some(1),
+                Some(2),
+                None,
+            ])),
+        ),
+        (
+            "amount",
+            Arc::new(
+                vec![Some(12345i128), None, Some(-1)]
+                    .into_iter()
+                    .collect::<Decimal128Array>()
+                    .with_precision_and_scale(10, 2)
+                    .unwrap(),
+            ),
+        ),
+        (
+            "hash",
+            Arc::new(
+                FixedSizeBinaryArray::try_from_iter(vec![[1u8, 2], [3, 4], [5, 6]].into_iter())
+                    .unwrap(),
+            ),
+        ),
+        (
+            "list",
+            Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(1), None]),
+                None,
+                Some(vec![]),
+            ])),
+        ),
+        (
+            "large_list",
+            Arc::new(LargeListArray::from_iter_primitive::<Int64Type, _, _>(
+                vec![Some(vec![Some(1)]), Some(vec![Some(2), Some(3)]), None],
+            )),
+        ),
+        ("point", Arc::new(point)),
+        ("value", Arc::new(values)),
+    ];
+    RecordBatch::try_from_iter(columns).unwrap()
+}
+
+/// The schema as an encapsulated IPC message, as in `FlightInfo.schema`.
+fn encapsulated_schema(schema: &Schema) -> Vec<u8> {
+    let message = IpcDataGenerator::default()
+        .schema_to_bytes(schema, &IpcWriteOptions::default())
+        .ipc_message;
+    let mut bytes = vec![0xff; 4];
+    bytes.extend_from_slice(&(message.len() as i32).to_le_bytes());
+    bytes.extend_from_slice(&message);
+    bytes
+}
+
+fn encode_batch(batch: &RecordBatch) -> Vec<FlightData> {
+    flight_data_from_arrow_batches(
+        FlightDescriptor::new_cmd(vec![]),
+        std::slice::from_ref(batch),
+    )
+    .unwrap()
+}
+
+fn category_schema() -> Schema {
+    Schema::new(vec![Field::new_dict(
+        "category",
+        DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+        true,
+        0,
+        false,
+    )])
+}
+
+/// A dictionary batch with the values, which are appended to the dictionary
+/// with the same id when `is_delta` is set.
+fn dictionary_message(id: i64, values: Vec<&str>, is_delta: bool) -> FlightData {
+    let values =
+        RecordBatch::try_from_iter(vec![("", Arc::new(StringArray::from(values)) as ArrayRef)])
+            .unwrap();
+    let (_, encoded) = IpcDataGenerator::default()
+        .encoded_batch(
+            &values,
+            &mut DictionaryTracker::new(false),
+            &IpcWriteOptions::default(),
+        )
+        .unwrap();
+    let message = ipc::root_as_message(&encoded.ipc_message).unwrap();
+    let batch = message.header_as_record_batch().unwrap();
+
+    let mut fbb = flatbuffers::FlatBufferBuilder::new();
+    let nodes = fbb.create_vector(batch.nodes().unwrap());
+    let buffers = fbb.create_vector(batch.buffers().unwrap());
+    let mut data = ipc::RecordBatchBuilder::new(&mut fbb);
+    data.add_length(batch.length());
+    data.add_nodes(nodes);
+    data.add_buffers(buffers);
+    let data = data.finish();
+    let mut dictionary = ipc::DictionaryBatchBuilder::new(&mut fbb);
+    dictionary.add_id(id);
+    dictionary.add_data(data);
+    dictionary.add_isDelta(is_delta);
+    let dictionary = dictionary.finish();
+    let mut message = ipc::MessageBuilder::new(&mut fbb);
+    message.add_version(ipc::MetadataVersion::V5);
+    message.add_header_type(ipc::MessageHeader::DictionaryBatch);
+    message.add_header(dictionary.as_union_value());
+    message.add_bodyLength(encoded.arrow_data.len() as i64);
+    let message = message.finish();
+    fbb.finish(message, None);
+
+    FlightData {
+        data_header: fbb.finished_data().to_vec(),
+        data_body: encoded.arrow_data,
+        ..Default::default()
+    }
+}
+
+/// A record batch of the keys of the category dictionary.
+fn category_message(keys: Vec<i32>) -> FlightData {
+    let placeholder = StringArray::from(vec![""; 8]);
+    let categories =
+        DictionaryArray::<Int32Type>::try_new(&Int32Array::from(keys), &placeholder).unwrap();
+    let batch =
+        RecordBatch::try_new(Arc::new(category_schema()), vec![Arc::new(categories)]).unwrap();
+    let (_, encoded) = IpcDataGenerator::default()
+        .encoded_batch(
+            &batch,
+            &mut DictionaryTracker::new(false),
+            &IpcWriteOptions::default(),
+        )
+        .unwrap();
+    FlightData {
+        data_header: encoded.ipc_message,
+        data_body: encoded.arrow_data,
+        ..Default::default()
+    }
+}
+
+/// Move every buffer of the record batch messages one byte further in the
+/// body, so that none of them is aligned.
+fn unaligned(messages: Vec<FlightData>) -> Vec<FlightData> {
+    messages
+        .into_iter()
+        .map(|message| {
+            let header = ipc::root_as_message(&message.data_header).unwrap();
+            let batch = match header.header_as_record_batch() {
+                Some(batch) => batch,
+                None => return message,
+            };
+            let mut fbb = flatbuffers::FlatBufferBuilder::new();
+            let nodes = fbb.create_vector(batch.nodes().unwrap());
+            let buffers = batch
+                .buffers()
+                .unwrap()
+                .iter()
+                .map(|buffer| ipc::Buffer::new(buffer.offset() + 1, buffer.length()))
+                .collect::<Vec<_>>();
+            let buffers = fbb.create_vector(&buffers);
+            let mut data = ipc::RecordBatchBuilder::new(&mut fbb);
+            data.add_length(batch.length());
+            data.add_nodes(nodes);
+            data.add_buffers(buffers);
+            let data = data.finish();
+            let mut builder = ipc::MessageBuilder::new(&mut fbb);
+            builder.add_version(header.version());
+            builder.add_header_type(ipc::MessageHeader::RecordBatch);
+            builder.add_header(data.as_union_value());
+            builder.add_bodyLength(header.bodyLength() + 1);
+            let builder = builder.finish();
+            fbb.finish(builder, None);
+
+            let mut body = vec![0];
+            body.extend_from_slice(&message.data_body);
+            FlightData {
+                data_header: fbb.finished_data().to_vec(),
+                data_body: body,
+                ..message
+            }
+        })
+        .collect()
+}
+
+/// The values of the category column of the batches.
+fn categories(batches: &[RecordBatch]) -> Vec<Option<String>> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            let values = cast(batch.column(0), &DataType::Utf8).unwrap();
+            let values = values.as_any().downcast_ref::<StringArray>().unwrap();
+            values
+                .iter()
+                .map(|value| value.map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Decode a stream of messages, returning the decoded batches or the first
+/// error.
+fn decode_stream(
+    schema: SchemaRef,
+    messages: Vec<FlightData>,
+) -> arrow::error::Result<Vec<RecordBatch>> {
+    let mut decoder = FlightDataDecoder::new(schema);
+    let mut batches = vec![];
+    for message in messages {
+        if let ArrowFlightData::RecordBatch(batch) = decoder.decode(message)? {
+            batches.push(batch);
+        }
+    }
+    Ok(batches)
+}
+
+/// Replace bytes at arbitrary positions, wrapping around the length.
+fn corrupt(mut bytes: Vec<u8>, changes: &[(usize, u8)]) -> Vec<u8> {
+    if !bytes.is_empty() {
+        let len = bytes.len();
+        for (index, byte) in changes {
+            bytes[index % len] = *byte;
+        }
+    }
+    bytes
+}
+
+fn changes() -> impl Strategy<Value = Vec<(usize, u8)>> {
+    prop::collection::vec((any::<usize>(), any::<u8>()), 1..8)
+}
+
+thread_local! {
+    static PANICKED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run a decoding function, failing the test case if it panics, even when
+/// the panic is caught by the code under test.
+fn without_panics<T>(decode: impl FnOnce() -> T) -> Result<T, TestCaseError> {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            PANICKED.with(|panicked| panicked.set(true));
+            default_hook(info);
+        }));
+    });
+    PANICKED.with(|panicked| panicked.set(false));
+    let result = decode();
+    prop_assert!(!PANICKED.with(Cell::get), "Decoding panicked");
+    Ok(result)
+}
+
+/// Check that decoded batches hold valid arrays.
+fn check_batches(batches: &[RecordBatch]) -> Result<(), TestCaseError> {
+    for batch in batches {
+        for column in batch.columns() {
+            prop_assert_eq!(column.len(), batch.num_rows());
+            prop_assert!(column.data().validate().is_ok());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn decodes_valid_schema() {
+    let bytes = encapsulated_schema(&test_schema());
+    let fi = FlightInfo {
+        schema: bytes.clone(),
+        ..Default::default()
+    };
+    assert_eq!(arrow_schema_from_flight_info(&fi).unwrap(), test_schema());
+    assert_eq!(Schema::try_from(IpcMessage(bytes)).unwrap(), test_schema());
+    // The legacy format has no continuation marker.
+    let legacy = encapsulated_schema(&test_schema())[4..].to_vec();
+    assert_eq!(Schema::try_from(IpcMessage(legacy)).unwrap(), test_schema());
+}
+
+#[test]
+fn rejects_short_schema() {
+    for len in 0..8 {
+        let bytes = encapsulated_schema(&test_schema())[..len].to_vec();
+        assert!(Schema::try_from(IpcMessage(bytes)).is_err());
+    }
+}
+
+#[test]
+fn decodes_valid_stream() {
+    let batch = test_batch();
+    let batches = decode_stream(batch.schema(), encode_batch(&batch)).unwrap();
+    assert_eq!(batches, vec![batch]);
+}
+
+#[test]
+fn decodes_valid_nested_stream() {
+    let batch = nested_batch();
+    let batches = decode_stream(batch.schema(), encode_batch(&batch)).unwrap();
+    assert_eq!(batches, vec![batch]);
+}
+
+#[test]
+fn decodes_unaligned_buffers() {
+    for batch in [test_batch(), nested_batch()] {
+        let messages = unaligned(encode_batch(&batch));
+        let batches = decode_stream(batch.schema(), messages).unwrap();
+        assert_eq!(batches, vec![batch]);
+    }
+}
+
+#[test]
+fn decodes_delta_dictionary() {
+    let batches = decode_stream(
+        Arc::new(category_schema()),
+        vec![
+            dictionary_message(0, vec!["a", "b"], false),
+            dictionary_message(0, vec!["c"], true),
+            category_message(vec![0, 2, 1, 2]),
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        categories(&batches),
+        vec![
+            Some("a".to_string()),
+            Some("c".to_string()),
+            Some("b".to_string()),
+            Some("c".to_string())
+        ]
+    );
+}
+
+#[test]
+fn decodes_replaced_dictionary() {
+    let batches = decode_stream(
+        Arc::new(category_schema()),
+        vec![
+            dictionary_message(0, vec!["a", "b", "c"], false),
+            category_message(vec![2, 0]),
+            dictionary_message(0, vec!["x", "y"], false),
+            category_message(vec![1, 0]),
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        categories(&batches),
+        vec![
+            Some("c".to_string()),
+            Some("a".to_string()),
+            Some("y".to_string()),
+            Some("x".to_string())
+        ]
+    );
+
+    // The keys of the replaced dictionary are no longer valid.
+    let result = decode_stream(
+        Arc::new(category_schema()),
+        vec![
+            dictionary_message(0, vec!["a", "b", "c"], false),
+            dictionary_message(0, vec!["x", "y"], false),
+            category_message(vec![2]),
+        ],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn decodes_valid_sql_info() {
+    let batch = sql_info_batch();
+    let batches = decode_stream(batch.schema(), encode_batch(&batch)).unwrap();
+    assert_eq!(decode_sql_info(&batches[0]).unwrap().len(), 3);
+}
+
+#[test]
+fn rejects_any_of_another_type() {
+    let bytes = DoPutUpdateResult { record_count: 3 }
+        .as_any()
+        .encode_to_vec();
+    assert_eq!(
+        decode_any::<DoPutUpdateResult>(&bytes)
+            .unwrap()
+            .record_count,
+        3
+    );
+    match decode_any::<ActionCreatePreparedStatementResult>(&bytes) {
+        Err(FlightSqlError::Protocol(_)) => {}
+        other => panic!("Expected a protocol error, found {:?}", other),
+    }
+}
+
+proptest! {
+    #[test]
+    fn schema_from_arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        let fi = FlightInfo { schema: bytes.clone(), ..Default::default() };
+        without_panics(|| arrow_schema_from_flight_info(&fi))?.ok();
+        without_panics(|| Schema::try_from(IpcMessage(bytes)))?.ok();
+    }
+
+    #[test]
+    fn schema_from_corrupted_message(changes in changes()) {
+        let bytes = corrupt(encapsulated_schema(&test_schema()), &changes);
+        without_panics(|| Schema::try_from(IpcMessage(bytes)))?.ok();
+    }
+
+    #[test]
+    fn schema_from_truncated_message(len in 0usize..512) {
+        let mut bytes = encapsulated_schema(&test_schema());
+        bytes.truncate(len);
+        if let Ok(schema) = without_panics(|| Schema::try_from(IpcMessage(bytes)))? {
+            prop_assert_eq!(schema, test_schema());
+        }
+    }
+
+    #[test]
+    fn any_from_arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+        without_panics(|| decode_any::<DoPutUpdateResult>(&bytes))?.ok();
+        without_panics(|| decode_any::<ActionCreatePreparedStatementResult>(&bytes))?.ok();
+    }
+
+    #[test]
+    fn any_with_arbitrary_type_url(type_url in ".*", value in prop::collection::vec(any::<u8>(), 0..64)) {
+        let bytes = prost_types::Any { type_url, value }.encode_to_vec();
+        without_panics(|| decode_any::<DoPutUpdateResult>(&bytes))?.ok();
+    }
+
+    #[test]
+    fn flight_data_from_arbitrary_bytes(
+        header in prop::collection::vec(any::<u8>(), 0..512),
+        body in prop::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let message = FlightData { data_header: header, data_body: body, ..Default::default() };
+        if let Ok(batches) = without_panics(|| decode_stream(Arc::new(test_schema()), vec![message]))? {
+            check_batches(&batches)?;
+        }
+    }
+
+    #[test]
+    fn flight_data_from_corrupted_stream(
+        message in 0usize..3,
+        header_changes in prop::collection::vec((any::<usize>(), any::<u8>()), 0..8),
+        body_changes in prop::collection::vec((any::<usize>(), any::<u8>()), 0..8),
+    ) {
+        let batch = test_batch();
+        let original = encode_batch(&batch);
+        let mut messages = original.clone();
+        let corrupted = &mut messages[message];
+        corrupted.data_header = corrupt(std::mem::take(&mut corrupted.data_header), &header_changes);
+        corrupted.data_body = corrupt(std::mem::take(&mut corrupted.data_body), &body_changes);
+        let unchanged = messages == original;
+        match without_panics(|| decode_stream(batch.schema(), messages))? {
+            Ok(batches) if unchanged => prop_assert_eq!(batches, vec![batch]),
+            Ok(batches) => check_batches(&batches)?,
+            Err(_) => prop_assert!(!unchanged),
+        }
+    }
+
+    #[test]
+    fn flight_data_from_truncated_stream(message in 0usize..3, body_len in 0usize..256) {
+        let batch = test_batch();
+        let mut messages = encode_batch(&batch);
+        messages[message].data_body.truncate(body_len);
+        if let Ok(batches) = without_panics(|| decode_stream(batch.schema(), messages))? {
+            prop_assert_eq!(batches, vec![batch]);
+        }
+    }
+
+    #[test]
+    fn nested_batch_from_corrupted_stream(
+        header_changes in prop::collection::vec((any::<usize>(), any::<u8>()), 0..4),
+        body_changes in prop::collection::vec((any::<usize>(), any::<u8>()), 0..8),
+        body_len in prop::option::of(0usize..512),
+    ) {
+        let batch = nested_batch();
+        let mut messages = encode_batch(&batch);
+        let corrupted = messages.last_mut().unwrap();
+        corrupted.data_header = corrupt(std::mem::take(&mut corrupted.data_header), &header_changes);
+        corrupted.data_body = corrupt(std::mem::take(&mut corrupted.data_body), &body_changes);
+        if let Some(len) = body_len {
+            corrupted.data_body.truncate(len);
+        }
+        if let Ok(batches) = without_panics(|| decode_stream(batch.schema(), messages))? {
+            check_batches(&batches)?;
+        }
+    }
+
+    #[test]
+    fn sql_info_from_corrupted_batch(body_changes in changes()) {
+        let batch = sql_info_batch();
+        let mut messages = encode_batch(&batch);
+        let corrupted = messages.last_mut().unwrap();
+        corrupted.data_body = corrupt(std::mem::take(&mut corrupted.data_body), &body_changes);
+        if let Ok(batches) = without_panics(|| decode_stream(batch.schema(), messages))? {
+            check_batches(&batches)?;
+            for batch in batches {
+                without_panics(|| decode_sql_info(&batch))?.ok();
+            }
+        }
+    }
+
+    #[test]
+    fn sql_info_from_truncated_batch(body_len in 0usize..256) {
+        let batch = sql_info_batch();
+        let mut messages = encode_batch(&batch);
+        messages.last_mut().unwrap().data_body.truncate(body_len);
+        if let Ok(batches) = without_panics(|| decode_stream(batch.schema(), messages))? {
+            prop_assert_eq!(batches, vec![batch]);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Tests of the stats the client records of its calls.
+
+mod common;
+
+use std::time::Duration;
+
+use arrow_flight_sql_client::{
+    arrow_flight_protocol::Ticket, client::FlightSqlServiceClient, connection::ClientBuilder,
+    retry::RetryPolicy,
+};
+use common::{Config, TestServer};
+use tonic::Code;
+
+async fn connect(server: &TestServer, max_attempts: u32) -> FlightSqlServiceClient {
+    ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        })
+        .connect()
+        .await
+        .unwrap()
+}
+
+fn ticket() -> Ticket {
+    Ticket {
+        ticket: b"ticket".to_vec(),
+    }
+}
+
+#[tokio::test]
+async fn records_query() {
+    let server = TestServer::start(Config::default()).await;
+    let client = connect(&server, 1).await;
+    let fi = client.execute("select 1".to_string()).await.unwrap();
+    client.do_get_batches(&fi).await.unwrap();
+
+    let stats = client.take_stats();
+    assert_eq!(stats.get_flight_info_calls, 1);
+    assert_eq!(stats.do_get_calls, 1);
+    assert_eq!(stats.batches, 2);
+    assert_eq!(stats.rows, 6);
+    assert!(stats.bytes > 0);
+    assert!(stats.time_to_first_batch.is_some());
+    assert!(stats.errors.is_empty());
+    assert_eq!(client.stats(), Default::default());
+}
+
+#[tokio::test]
+async fn counts_retried_do_get_once() {
+    let server = TestServer::start(Config {
+        failures: 2,
+        ..Default::default()
+    })
+    .await;
+    let client = connect(&server, 3).await;
+    client.do_get(ticket()).await.unwrap();
+
+    assert_eq!(server.count("DoGet"), 3);
+    let stats = client.stats();
+    assert_eq!(stats.do_get_calls, 1);
+    assert_eq!(stats.errors.get(&Code::Unavailable), Some(&2));
+}
+
+#[tokio::test]
+async fn does_not_count_failed_do_get() {
+    let server = TestServer::start(Config {
+        failures: 5,
+        ..Default::default()
+    })
+    .await;
+    let client = connect(&server, 2).await;
+    assert!(client.do_get(ticket()).await.is_err());
+
+    let stats = client.stats();
+    assert_eq!(stats.do_get_calls, 0);
+    assert_eq!(stats.errors.get(&Code::Unavailable), Some(&2));
+}
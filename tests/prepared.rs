@@ -0,0 +1,106 @@
+//! Tests of the parameters sent with prepared statements.
+
+mod common;
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, Int64Array},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use arrow_flight_sql_client::{
+    arrow_flight_protocol_sql::{CommandPreparedStatementQuery, CommandPreparedStatementUpdate},
+    client::{decode_any, FlightSqlServiceClient},
+    connection::ClientBuilder,
+};
+use common::{test_batch, Config, TestServer};
+
+fn parameter_schema() -> Schema {
+    Schema::new(vec![Field::new("id", DataType::Int64, true)])
+}
+
+fn parameters(ids: Vec<i64>) -> RecordBatch {
+    RecordBatch::try_new(
+        Arc::new(parameter_schema()),
+        vec![Arc::new(Int64Array::from(ids)) as ArrayRef],
+    )
+    .unwrap()
+}
+
+async fn start() -> (TestServer, FlightSqlServiceClient) {
+    let server = TestServer::start(Config {
+        parameter_schema: Some(parameter_schema()),
+        ..Default::default()
+    })
+    .await;
+    let client = ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn parameters_are_sent_before_the_query() {
+    let (server, client) = start().await;
+    let mut statement = client
+        .prepare("select * from t where id = ?".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        statement.parameter_schema().await.unwrap(),
+        &parameter_schema()
+    );
+    statement
+        .set_parameters(parameters(vec![1, 2, 3]))
+        .await
+        .unwrap();
+    let fi = statement.execute().await.unwrap();
+    let batches = client.do_get_batches(&fi).await.unwrap();
+    assert_eq!(batches, vec![test_batch(0), test_batch(1)]);
+
+    let methods = server
+        .calls()
+        .iter()
+        .map(|call| call.method)
+        .collect::<Vec<_>>();
+    assert_eq!(methods, ["DoAction", "DoPut", "GetFlightInfo", "DoGet"]);
+
+    let puts = server.puts();
+    assert_eq!(puts.len(), 1);
+    let command: CommandPreparedStatementQuery =
+        decode_any(&puts[0].descriptor.as_ref().unwrap().cmd).unwrap();
+    assert_eq!(command.prepared_statement_handle, b"handle");
+    assert_eq!(puts[0].schema.as_ref(), Some(&parameter_schema()));
+    assert_eq!(puts[0].batches, vec![parameters(vec![1, 2, 3])]);
+}
+
+#[tokio::test]
+async fn update_sends_every_parameter_batch() {
+    let (server, client) = start().await;
+    let mut statement = client
+        .prepare("delete from t where id = ?".to_string())
+        .await
+        .unwrap();
+    let batches = vec![
+        parameters(vec![1, 2]),
+        parameters(vec![3]),
+        parameters(vec![4, 5, 6]),
+    ];
+    statement
+        .set_parameter_batches(batches.clone())
+        .await
+        .unwrap();
+    // The test server counts one updated record per parameter row.
+    assert_eq!(statement.execute_update().await.unwrap(), 6);
+
+    let puts = server.puts();
+    assert_eq!(puts.len(), 1);
+    let command: CommandPreparedStatementUpdate =
+        decode_any(&puts[0].descriptor.as_ref().unwrap().cmd).unwrap();
+    assert_eq!(command.prepared_statement_handle, b"handle");
+    assert_eq!(puts[0].schema.as_ref(), Some(&parameter_schema()));
+    assert_eq!(puts[0].batches, batches);
+}
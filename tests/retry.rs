@@ -0,0 +1,117 @@
+//! Tests of the retries of the calls that are safe to repeat.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use arrow_flight_sql_client::{
+    client::FlightSqlServiceClient, connection::ClientBuilder, error::FlightSqlError,
+    retry::RetryPolicy,
+};
+use common::{test_batch, Config, TestServer};
+use tonic::Code;
+
+const BACKOFF: Duration = Duration::from_millis(50);
+
+/// Connect with a policy of three attempts and backoffs of 50 and 100 ms.
+async fn connect(server: &TestServer) -> FlightSqlServiceClient {
+    ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: BACKOFF,
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: 0.0,
+        })
+        .connect()
+        .await
+        .unwrap()
+}
+
+async fn start(failures: usize, failing_method: &'static str) -> TestServer {
+    TestServer::start(Config {
+        failures,
+        failing_method: Some(failing_method),
+        ..Default::default()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn metadata_call_is_retried_with_backoff() {
+    let server = start(2, "GetFlightInfo").await;
+    let client = connect(&server).await;
+    let started = Instant::now();
+    client.get_catalogs().await.unwrap();
+    assert!(started.elapsed() >= BACKOFF * 3, "{:?}", started.elapsed());
+    assert_eq!(server.count("GetFlightInfo"), 3);
+}
+
+#[tokio::test]
+async fn metadata_call_fails_after_max_attempts() {
+    let server = start(3, "GetFlightInfo").await;
+    let client = connect(&server).await;
+    let err = client.get_table_types().await.unwrap_err();
+    assert_eq!(err.code(), Some(Code::Unavailable));
+    assert_eq!(server.count("GetFlightInfo"), 3);
+}
+
+#[tokio::test]
+async fn do_get_is_retried() {
+    let server = start(2, "DoGet").await;
+    let client = connect(&server).await;
+    let fi = client.execute("select 1".to_string()).await.unwrap();
+    let batches = client.do_get_batches(&fi).await.unwrap();
+    assert_eq!(batches, vec![test_batch(0), test_batch(1)]);
+    assert_eq!(server.count("GetFlightInfo"), 1);
+    assert_eq!(server.count("DoGet"), 3);
+}
+
+#[tokio::test]
+async fn invalid_argument_is_not_retried() {
+    let server = TestServer::start(Config {
+        failures: 1,
+        failure_code: Code::InvalidArgument,
+        ..Default::default()
+    })
+    .await;
+    let client = connect(&server).await;
+    let err = client.get_catalogs().await.unwrap_err();
+    assert_eq!(err.code(), Some(Code::InvalidArgument));
+    assert_eq!(server.count("GetFlightInfo"), 1);
+}
+
+#[tokio::test]
+async fn query_is_not_retried() {
+    let server = start(1, "GetFlightInfo").await;
+    let client = connect(&server).await;
+    let err = client.execute("select 1".to_string()).await.unwrap_err();
+    assert_eq!(err.code(), Some(Code::Unavailable));
+    assert_eq!(server.count("GetFlightInfo"), 1);
+}
+
+#[tokio::test]
+async fn update_is_never_retried() {
+    let server = start(1, "DoPut").await;
+    let client = connect(&server).await;
+    let err = client
+        .execute_update("delete from t".to_string())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(Code::Unavailable));
+    assert_eq!(server.count("DoPut"), 1);
+}
+
+#[tokio::test]
+async fn prepared_update_is_never_retried() {
+    let server = start(1, "DoPut").await;
+    let client = connect(&server).await;
+    let statement = client.prepare("delete from t".to_string()).await.unwrap();
+    let err = statement.execute_update().await.unwrap_err();
+    assert!(matches!(&err, FlightSqlError::Status(status) if status.code() == Code::Unavailable));
+    assert_eq!(server.count("DoPut"), 1);
+
+    // The update succeeds once the server is available again.
+    assert_eq!(statement.execute_update().await.unwrap(), 1);
+}
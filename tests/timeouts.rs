@@ -0,0 +1,96 @@
+//! Tests of the timeouts of calls and of the streams of their results.
+
+mod common;
+
+use std::time::Duration;
+
+use arrow_flight_sql_client::{
+    client::{DoGetOptions, FlightSqlServiceClient},
+    connection::ClientBuilder,
+    error::FlightSqlError,
+};
+use common::{test_batch, Config, TestServer};
+use futures::TryStreamExt;
+
+async fn connect(server: &TestServer, timeout: Duration) -> FlightSqlServiceClient {
+    ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .with_timeout(timeout)
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn slow_stream_outlives_the_call_timeout() {
+    let server = TestServer::start(Config {
+        batches: 3,
+        batch_delay: Duration::from_millis(300),
+        ..Default::default()
+    })
+    .await;
+    let client = connect(&server, Duration::from_millis(500)).await;
+    let fi = client.execute("select 1".to_string()).await.unwrap();
+    let options = DoGetOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+    let batches = client
+        .do_get_endpoints(&fi, &options)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(batches, vec![test_batch(0), test_batch(1), test_batch(2)]);
+
+    // The deadline is only sent with the calls that are not streams.
+    for call in server.calls() {
+        let deadline = call.metadata.get("grpc-timeout");
+        assert_eq!(
+            deadline.is_some(),
+            call.method != "DoGet",
+            "{}",
+            call.method
+        );
+    }
+}
+
+#[tokio::test]
+async fn stalled_stream_hits_the_idle_timeout() {
+    let server = TestServer::start(Config {
+        stall: true,
+        ..Default::default()
+    })
+    .await;
+    let client = connect(&server, Duration::from_secs(30)).await;
+    let fi = client.execute("select 1".to_string()).await.unwrap();
+    let options = DoGetOptions {
+        idle_timeout: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+    let mut stream = client.do_get_endpoints(&fi, &options).await.unwrap();
+    assert_eq!(stream.try_next().await.unwrap(), Some(test_batch(0)));
+    let err = tokio::time::timeout(Duration::from_secs(5), stream.try_next())
+        .await
+        .expect("The idle timeout did not end the stream")
+        .unwrap_err();
+    assert!(matches!(err, FlightSqlError::Timeout(_)), "{}", err);
+}
+
+#[tokio::test]
+async fn update_is_sent_without_deadline() {
+    let server = TestServer::start(Config::default()).await;
+    let client = connect(&server, Duration::from_secs(30)).await;
+    assert_eq!(
+        client
+            .execute_update("update t set x = 1".to_string())
+            .await
+            .unwrap(),
+        1
+    );
+    let calls = server.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].method, "DoPut");
+    assert!(calls[0].metadata.get("grpc-timeout").is_none());
+}
@@ -0,0 +1,44 @@
+//! Tests of the trace context sent to the server.
+
+mod common;
+
+use arrow_flight_sql_client::connection::ClientBuilder;
+use common::{Config, TestServer};
+use opentelemetry::{
+    global,
+    sdk::{propagation::TraceContextPropagator, trace::TracerProvider},
+    trace::{TraceContextExt, TracerProvider as _},
+};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+#[tokio::test]
+async fn trace_context_of_the_current_span_is_sent() {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = TracerProvider::builder().build();
+    let subscriber = Registry::default()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let server = TestServer::start(Config::default()).await;
+    let client = ClientBuilder::from_uri(&server.uri())
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let span = tracing::info_span!("query");
+    let trace_id = span.context().span().span_context().trace_id();
+    client.get_catalogs().instrument(span).await.unwrap();
+
+    let calls = server.calls();
+    let traceparent = calls[0]
+        .metadata
+        .get("traceparent")
+        .expect("No traceparent sent")
+        .to_str()
+        .unwrap();
+    let fields = traceparent.split('-').collect::<Vec<_>>();
+    assert_eq!(fields.len(), 4, "{}", traceparent);
+    assert_eq!(fields[1], format!("{:032x}", trace_id));
+}